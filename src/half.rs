@@ -0,0 +1,369 @@
+//! 16 bit floating point element types for compact matrices.
+//!
+//! `f16` is IEEE 754 binary16 (5 exponent and 10 mantissa bits), `bf16` is bfloat16
+//! (8 exponent and 7 mantissa bits, the range of `f32` with less precision). Both only
+//! store values: arithmetic is done in `f32` and rounded back to nearest, ties to even, so
+//! every single operation is correctly rounded. Matrix products widen their operands to
+//! `f32` and only round the finished sums, see `gemm`.
+
+#![allow(non_camel_case_types)]
+
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    num::FpCategory,
+    ops::{Add, Div, Mul, Neg, Rem, Sub},
+    str::FromStr,
+};
+
+use num::{Float, Num, NumCast, One, ToPrimitive, Zero};
+
+#[derive(Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct f16(u16);
+
+#[derive(Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct bf16(u16);
+
+/// Adds one to the truncated `bits` when the dropped part is more than half an ulp, or exactly
+/// half with an odd result.
+fn round_nearest_even(bits: u32, dropped: u32, shift: u32) -> u32 {
+    let half = 1 << (shift - 1);
+    let dropped = dropped & ((1 << shift) - 1);
+    if dropped > half || (dropped == half && bits & 1 == 1) {
+        bits + 1
+    } else {
+        bits
+    }
+}
+
+/// `x` rounded to `f32` with round-to-odd: an inexact result gets its last mantissa bit set.
+///
+/// `f32` keeps more than two bits beyond `f16` and `bf16`, so rounding this once more to nearest
+/// gives the same result as rounding `x` directly, where `x as f32` could round twice.
+fn round_to_odd(x: f64) -> f32 {
+    let y = x as f32;
+    let bits = y.to_bits();
+    if !y.is_finite() || y as f64 == x || bits & 1 == 1 {
+        return y;
+    }
+    if (y as f64).abs() > x.abs() {
+        f32::from_bits(bits - 1)
+    } else {
+        f32::from_bits(bits + 1)
+    }
+}
+
+impl f16 {
+    pub const EPSILON: Self = Self(0x1400);
+    pub const MAX: Self = Self(0x7bff);
+    pub const MIN_POSITIVE: Self = Self(0x0400);
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xff) as i32;
+        let mantissa = bits & 0x7f_ffff;
+
+        if exponent == 0xff {
+            let nan = if mantissa != 0 {
+                0x200 | (mantissa >> 13) as u16
+            } else {
+                0
+            };
+            return Self(sign | 0x7c00 | nan);
+        }
+        let exponent = exponent - 127 + 15;
+        if exponent >= 0x1f {
+            return Self(sign | 0x7c00);
+        }
+        if exponent <= 0 {
+            // Subnormal, anything below half the smallest one rounds to zero.
+            if exponent < -10 {
+                return Self(sign);
+            }
+            let full = mantissa | 0x80_0000;
+            let shift = (14 - exponent) as u32;
+            return Self(sign | round_nearest_even(full >> shift, full, shift) as u16);
+        }
+        // A carry out of the mantissa correctly bumps the exponent, up to infinity.
+        let truncated = ((exponent as u32) << 10) | (mantissa >> 13);
+        Self(sign | round_nearest_even(truncated, mantissa, 13) as u16)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        let sign = ((self.0 & 0x8000) as u32) << 16;
+        let exponent = ((self.0 >> 10) & 0x1f) as u32;
+        let mantissa = (self.0 & 0x3ff) as u32;
+        match exponent {
+            0 => {
+                let magnitude = mantissa as f32 * (-24f32).exp2();
+                f32::from_bits(sign | magnitude.to_bits())
+            }
+            0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+            _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+        }
+    }
+}
+
+impl bf16 {
+    pub const EPSILON: Self = Self(0x3c00);
+    pub const MAX: Self = Self(0x7f7f);
+    pub const MIN_POSITIVE: Self = Self(0x0080);
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        if x.is_nan() {
+            // Keep it a NaN even when the payload is only in the dropped bits.
+            return Self((bits >> 16) as u16 | 0x40);
+        }
+        Self(round_nearest_even(bits >> 16, bits, 16) as u16)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        f32::from_bits((self.0 as u32) << 16)
+    }
+}
+
+macro_rules! impl_half {
+    ($half:ident) => {
+        impl $half {
+            pub fn from_f64(x: f64) -> Self {
+                Self::from_f32(round_to_odd(x))
+            }
+            pub fn to_f64(self) -> f64 {
+                self.to_f32() as f64
+            }
+        }
+
+        impl From<$half> for f32 {
+            fn from(x: $half) -> f32 {
+                x.to_f32()
+            }
+        }
+        impl From<$half> for f64 {
+            fn from(x: $half) -> f64 {
+                x.to_f64()
+            }
+        }
+        impl From<f32> for $half {
+            fn from(x: f32) -> Self {
+                Self::from_f32(x)
+            }
+        }
+
+        impl PartialEq for $half {
+            fn eq(&self, other: &Self) -> bool {
+                $half::to_f32(*self) == $half::to_f32(*other)
+            }
+        }
+        impl PartialOrd for $half {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                $half::to_f32(*self).partial_cmp(&$half::to_f32(*other))
+            }
+        }
+
+        impl Debug for $half {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                Debug::fmt(&$half::to_f32(*self), f)
+            }
+        }
+        impl Display for $half {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                Display::fmt(&$half::to_f32(*self), f)
+            }
+        }
+        impl FromStr for $half {
+            type Err = std::num::ParseFloatError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse::<f32>().map(Self::from_f32)
+            }
+        }
+
+        impl_half!(@binary $half, Add, add, +);
+        impl_half!(@binary $half, Sub, sub, -);
+        impl_half!(@binary $half, Mul, mul, *);
+        impl_half!(@binary $half, Div, div, /);
+        impl_half!(@binary $half, Rem, rem, %);
+
+        impl Neg for $half {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(self.0 ^ 0x8000)
+            }
+        }
+
+        impl Zero for $half {
+            fn zero() -> Self {
+                Self(0)
+            }
+            fn is_zero(&self) -> bool {
+                self.0 & 0x7fff == 0
+            }
+        }
+        impl One for $half {
+            fn one() -> Self {
+                Self::from_f32(1.)
+            }
+        }
+        impl Num for $half {
+            type FromStrRadixErr = num::traits::ParseFloatError;
+
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                f32::from_str_radix(s, radix).map(Self::from_f32)
+            }
+        }
+
+        impl ToPrimitive for $half {
+            fn to_i64(&self) -> Option<i64> {
+                $half::to_f32(*self).to_i64()
+            }
+            fn to_u64(&self) -> Option<u64> {
+                $half::to_f32(*self).to_u64()
+            }
+            fn to_f32(&self) -> Option<f32> {
+                Some($half::to_f32(*self))
+            }
+            fn to_f64(&self) -> Option<f64> {
+                Some($half::to_f64(*self))
+            }
+        }
+        impl NumCast for $half {
+            fn from<N: ToPrimitive>(n: N) -> Option<Self> {
+                n.to_f64().map(Self::from_f64)
+            }
+        }
+
+        impl Float for $half {
+            fn nan() -> Self {
+                Self::from_f32(f32::NAN)
+            }
+            fn infinity() -> Self {
+                Self::from_f32(f32::INFINITY)
+            }
+            fn neg_infinity() -> Self {
+                Self::from_f32(f32::NEG_INFINITY)
+            }
+            fn neg_zero() -> Self {
+                Self(0x8000)
+            }
+            fn min_value() -> Self {
+                -Self::MAX
+            }
+            fn min_positive_value() -> Self {
+                Self::MIN_POSITIVE
+            }
+            fn epsilon() -> Self {
+                Self::EPSILON
+            }
+            fn max_value() -> Self {
+                Self::MAX
+            }
+            fn classify(self) -> FpCategory {
+                match self.to_f32().classify() {
+                    // Smallest f16 values are normal f32 values.
+                    FpCategory::Normal if self < Self::MIN_POSITIVE && self > -Self::MIN_POSITIVE => {
+                        FpCategory::Subnormal
+                    }
+                    category => category,
+                }
+            }
+            fn is_nan(self) -> bool {
+                self.to_f32().is_nan()
+            }
+            fn is_infinite(self) -> bool {
+                self.to_f32().is_infinite()
+            }
+            fn is_finite(self) -> bool {
+                self.to_f32().is_finite()
+            }
+            fn is_normal(self) -> bool {
+                self.classify() == FpCategory::Normal
+            }
+            fn is_sign_positive(self) -> bool {
+                self.0 & 0x8000 == 0
+            }
+            fn is_sign_negative(self) -> bool {
+                self.0 & 0x8000 != 0
+            }
+            fn abs(self) -> Self {
+                Self(self.0 & 0x7fff)
+            }
+            fn integer_decode(self) -> (u64, i16, i8) {
+                self.to_f32().integer_decode()
+            }
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                Self::from_f32(self.to_f32().mul_add(a.to_f32(), b.to_f32()))
+            }
+            fn powi(self, n: i32) -> Self {
+                Self::from_f32(self.to_f32().powi(n))
+            }
+            fn powf(self, n: Self) -> Self {
+                Self::from_f32(self.to_f32().powf(n.to_f32()))
+            }
+            fn log(self, base: Self) -> Self {
+                Self::from_f32(self.to_f32().log(base.to_f32()))
+            }
+            fn max(self, other: Self) -> Self {
+                Self::from_f32(self.to_f32().max(other.to_f32()))
+            }
+            fn min(self, other: Self) -> Self {
+                Self::from_f32(self.to_f32().min(other.to_f32()))
+            }
+            #[allow(deprecated)]
+            fn abs_sub(self, other: Self) -> Self {
+                Self::from_f32((self.to_f32() - other.to_f32()).max(0.))
+            }
+            fn hypot(self, other: Self) -> Self {
+                Self::from_f32(self.to_f32().hypot(other.to_f32()))
+            }
+            fn atan2(self, other: Self) -> Self {
+                Self::from_f32(self.to_f32().atan2(other.to_f32()))
+            }
+            fn sin_cos(self) -> (Self, Self) {
+                let (sin, cos) = self.to_f32().sin_cos();
+                (Self::from_f32(sin), Self::from_f32(cos))
+            }
+
+            impl_half!(@unary floor ceil round trunc fract signum recip sqrt exp exp2 ln log2
+                log10 cbrt sin cos tan asin acos atan exp_m1 ln_1p sinh cosh tanh asinh acosh
+                atanh);
+        }
+    };
+    (@binary $half:ident, $trait:ident, $method:ident, $op:tt) => {
+        impl $trait for $half {
+            type Output = Self;
+
+            fn $method(self, rhs: Self) -> Self {
+                Self::from_f32(self.to_f32() $op rhs.to_f32())
+            }
+        }
+    };
+    (@unary $($method:ident)+) => {
+        $(
+            fn $method(self) -> Self {
+                Self::from_f32(self.to_f32().$method())
+            }
+        )+
+    };
+}
+
+impl_half!(f16);
+impl_half!(bf16);
@@ -1,6 +1,6 @@
 use std::{fmt::Display, fs::File, io::Read};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use num::Float;
 
 use crate::matrix::Matrix2d;
@@ -19,7 +19,18 @@ impl<T:Clone + Float> Img<T> {
     }
 }
 
+/// Reads MNIST style 28x28 images, one `label,pixel,...` line per image after a header line.
 pub fn csv_to_imgs<T: Clone + Float>(file: &mut File, number_of_images: usize) -> Result<Vec<Img<T>>> {
+    csv_to_imgs_shaped(file, number_of_images, 28, 28)
+}
+
+/// Same as `csv_to_imgs` for `rows x columns` images whose pixels are stored row by row.
+pub fn csv_to_imgs_shaped<T: Clone + Float>(
+    file: &mut File,
+    number_of_images: usize,
+    rows: usize,
+    columns: usize,
+) -> Result<Vec<Img<T>>> {
     let mut content = String::new();
     file.read_to_string(&mut content)?;
 
@@ -28,10 +39,10 @@ pub fn csv_to_imgs<T: Clone + Float>(file: &mut File, number_of_images: usize) -
     let mut imgs = Vec::new();
     for line in lines.skip(1).take(number_of_images) {
         let mut nums = line.split(',');
-        let mut img = Img::new(28, 28);
+        let mut img = Img::new(rows, columns);
         img.label = nums.next().unwrap().parse::<u32>()?;
-        for (i, num) in nums.enumerate() {
-            let rows = img.matrix.rows();
+        let mut pixels = 0;
+        for num in nums {
             let n = match num.trim().parse::<i32>() {
                 Ok(n) => Some(n),
                 Err(e) => match e.kind() {
@@ -39,10 +50,18 @@ pub fn csv_to_imgs<T: Clone + Float>(file: &mut File, number_of_images: usize) -
                     _ => return Err(e.into()),
                 },
             };
+            // Empty fields, e.g. after a trailing comma, are not pixels.
             if let Some(n) = n {
-                img.matrix[i / rows][i % rows] = (T::from(n).unwrap()) / T::from(256.0).unwrap();
+                if pixels == rows * columns {
+                    return Err(anyhow!("image has more than {rows}x{columns} pixels"));
+                }
+                img.matrix[pixels / columns][pixels % columns] = (T::from(n).unwrap()) / T::from(256.0).unwrap();
+                pixels += 1;
             }
         }
+        if pixels != rows * columns {
+            return Err(anyhow!("image has {pixels} pixels instead of {rows}x{columns}"));
+        }
         imgs.push(img);
     }
     Ok(imgs)
@@ -51,7 +70,7 @@ pub fn csv_to_imgs<T: Clone + Float>(file: &mut File, number_of_images: usize) -
 const SHADES: [char; 5] = [' ', '░', '▒','▓', '█'];
 
 fn normalize<T: Float>(v: T) -> T {
-    return v * T::from(4.).unwrap();
+    v * T::from(4.).unwrap()
 }
 
 impl<T:Clone + Float> Display for Img<T> {
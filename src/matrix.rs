@@ -1,35 +1,94 @@
-use core::panic;
 use std::{
-    error::Error, fmt::{Debug, Display}, fs::OpenOptions, io::{Read, Write}, ops::{Add, Index, IndexMut, Mul, Sub}, str::FromStr, usize
+    fmt::Debug, fs::OpenOptions, io::{Read, Write}, ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign}
 };
 
 use anyhow::{anyhow, Result};
-use num::{Float, ToPrimitive};
+use num::Float;
+use rand::Rng;
+#[cfg(feature = "rayon")]
 use rayon::iter::{
-    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
-    IntoParallelRefMutIterator, ParallelIterator,
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
 };
 
-use crate::uniform_distribution;
+
+pub use self::broadcast::broadcast_shape;
+pub use self::dispatch::{parallel_threshold, set_parallel_threshold, DEFAULT_PARALLEL_THRESHOLD};
+pub use self::eigen::{Convergence, Svd, SymmetricEigen};
+pub use self::error::{IndexError, ShapeError};
+pub use self::init::Initializer;
+pub use self::linalg::{Cholesky, LinalgError, Lu, Qr};
+pub use self::mask::Mask;
+pub use self::npy::{Endian, NpyDtype, NpyOptions};
+pub use self::view::{AsView, MatrixView, MatrixViewMut};
+
+pub(crate) use self::dispatch::par_if;
+#[cfg(feature = "rayon")]
+pub(crate) use self::dispatch::parallel;
+pub(crate) use self::error::or_panic;
+
+mod broadcast;
+mod dispatch;
+mod display;
+mod eigen;
+mod elementwise;
+mod error;
+mod gemm;
+mod init;
+mod iter;
+mod linalg;
+mod mask;
+mod npy;
+mod npz;
+mod reduce;
+mod stack;
+mod view;
+
+/// Builds a `Matrix2d` from rows separated by `;`, `matrix![1., 2.; 3., 4.]` is 2x2.
+#[macro_export]
+macro_rules! matrix {
+    ($($($x:expr),+ $(,)?);+ $(;)?) => {
+        $crate::matrix::Matrix2d::from_rows(&[$([$($x),+]),+])
+    };
+}
 
 pub fn i_to_xy(i: usize, columns: usize) -> (usize, usize) {
-    return (i / columns ,i % columns, );
+    (i / columns ,i % columns, )
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Axis {
     Row,
     Collumn,
 }
 
-trait MatrixBase: Clone + Float + Sized + Send {}
+/// Order in which the elements of a matrix are laid out one after another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryOrder {
+    /// Row after row, the order of `Matrix2d` itself.
+    #[default]
+    RowMajor,
+    /// Column after column, as in Fortran.
+    ColumnMajor,
+}
 
 #[derive(Debug, Clone)]
 pub struct Matrix2d<T: Clone> {
-    inner: Vec<T>,
-    rows: usize,
-    columns: usize,
+    pub(crate) inner: Vec<T>,
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
 }
+impl<T: Clone> Matrix2d<T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.columns)
+    }
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+}
+
 impl<T: Float + Sized> Matrix2d<T> {
     pub fn new(rows: usize, columns: usize) -> Self {
         Self {
@@ -38,50 +97,126 @@ impl<T: Float + Sized> Matrix2d<T> {
             columns,
         }
     }
+    pub fn zeros(rows: usize, columns: usize) -> Self {
+        Self::full(rows, columns, T::zero())
+    }
+    pub fn ones(rows: usize, columns: usize) -> Self {
+        Self::full(rows, columns, T::one())
+    }
+    pub fn full(rows: usize, columns: usize, value: T) -> Self {
+        Self {
+            inner: vec![value; rows * columns],
+            rows,
+            columns,
+        }
+    }
+    pub fn identity(n: usize) -> Self {
+        Self::from_fn(n, n, |r, c| if r == c { T::one() } else { T::zero() })
+    }
+    /// Takes ownership of row-major `data`, which must hold exactly `rows * columns` elements.
+    pub fn try_from_vec(rows: usize, columns: usize, data: Vec<T>) -> Result<Self, ShapeError> {
+        if data.len() != rows * columns {
+            return Err(ShapeError::new("from_vec", (rows, columns), (1, data.len())));
+        }
+        Ok(Self {
+            inner: data,
+            rows,
+            columns,
+        })
+    }
+    pub fn from_vec(rows: usize, columns: usize, data: Vec<T>) -> Self {
+        or_panic(Self::try_from_vec(rows, columns, data))
+    }
+    /// Element `(r, c)` is `fun(r, c)`.
+    pub fn from_fn<F>(rows: usize, columns: usize, mut fun: F) -> Self
+    where
+        F: FnMut(usize, usize) -> T,
+    {
+        let inner = (0..rows * columns)
+            .map(|i| fun(i / columns, i % columns))
+            .collect();
+        Self {
+            inner,
+            rows,
+            columns,
+        }
+    }
+    pub fn from_rows<const N: usize>(rows: &[[T; N]]) -> Self {
+        Self {
+            inner: rows.iter().flatten().copied().collect(),
+            rows: rows.len(),
+            columns: N,
+        }
+    }
+    /// Converts every element to `U`, rounding to nearest when `U` is less precise.
+    pub fn cast<U: Float>(&self) -> Matrix2d<U> {
+        Matrix2d {
+            inner: self.inner.iter().map(|x| U::from(*x).unwrap()).collect(),
+            rows: self.rows,
+            columns: self.columns,
+        }
+    }
+    /// Elements drawn uniformly from `low..high`.
+    pub fn random<R: Rng + ?Sized>(rows: usize, columns: usize, low: T, high: T, rng: &mut R) -> Self {
+        Initializer::Uniform { low, high }.sample(rows, columns, rng)
+    }
 
     pub fn fill(&mut self, n: T) {
         for i in 0..self.inner.len() {
             self.inner[i] = n;
         }
     }
-    pub fn flatten(&self, axis: Axis) -> Self {
-        let mut mat = match axis {
-            Axis::Row => Self::new(self.columns * self.rows, 1),
-            Axis::Collumn => Self::new(1, self.columns * self.rows),
-        };
-        for r in 0..self.rows {
-            for c in 0..self.columns {
-                mat.inner[r * self.columns + c] = self.inner[r * self.columns + c]
-            }
+    /// Elements in `order`, row by row for `RowMajor` and column by column for `ColumnMajor`.
+    fn ordered(&self, order: MemoryOrder) -> Vec<T> {
+        match order {
+            MemoryOrder::RowMajor => self.inner.clone(),
+            MemoryOrder::ColumnMajor => self.transpose().inner,
         }
-
-        mat
     }
-
-    pub fn argmax(&self) -> Result<usize, ()> {
-        if self.rows != 1 && self.columns != 1 {
-            return Err(());
+    /// All elements as an `n x 1` column, read in `order`.
+    pub fn flatten(&self, order: MemoryOrder) -> Self {
+        Self::from_vec(self.inner.len(), 1, self.ordered(order))
+    }
+    /// All elements as a `1 x n` row, read in `order`.
+    pub fn flatten_row(&self, order: MemoryOrder) -> Self {
+        Self::from_vec(1, self.inner.len(), self.ordered(order))
+    }
+    /// Same elements in a `rows x columns` matrix, read and written in row-major order.
+    pub fn try_reshape(&self, rows: usize, columns: usize) -> Result<Self, ShapeError> {
+        self.try_reshape_with(rows, columns, MemoryOrder::RowMajor)
+    }
+    pub fn reshape(&self, rows: usize, columns: usize) -> Self {
+        or_panic(self.try_reshape(rows, columns))
+    }
+    /// Same elements in a `rows x columns` matrix, read from `self` and written to the result in `order`.
+    pub fn try_reshape_with(
+        &self,
+        rows: usize,
+        columns: usize,
+        order: MemoryOrder,
+    ) -> Result<Self, ShapeError> {
+        if rows * columns != self.inner.len() {
+            return Err(ShapeError::new("reshape", self.shape(), (rows, columns)));
         }
-
-        let mut max_value = T::min_value();
-
-        let mut max_index = 0;
-
-        for (i, v) in self.inner.iter().enumerate() {
-            if *v > max_value {
-                max_value = *v;
-                max_index = i;
+        Ok(match order {
+            MemoryOrder::RowMajor => Self::from_vec(rows, columns, self.inner.clone()),
+            MemoryOrder::ColumnMajor => {
+                Self::from_vec(columns, rows, self.ordered(order)).transpose()
             }
-        }
-
-        Ok(max_index)
+        })
     }
-    pub fn randomize(&mut self, n: usize) -> Result<()> {
-        let min = T::from(-1.0 / n as f64).ok_or(anyhow!("Failed to convert from f64"))?;
-        let max = T::from(1.0 / n as f64).ok_or(anyhow!("Failed to convert from f64"))?;
-        for entry in self.inner.iter_mut() {
-            *entry = uniform_distribution(min, max)?;
-        }
+    pub fn reshape_with(&self, rows: usize, columns: usize, order: MemoryOrder) -> Self {
+        or_panic(self.try_reshape_with(rows, columns, order))
+    }
+
+    /// Uniform in `±1/n`, `initialize` takes other distributions.
+    pub fn randomize<R: Rng + ?Sized>(&mut self, n: usize, rng: &mut R) -> Result<()> {
+        let limit = T::from(1.0 / n as f64).ok_or(anyhow!("Failed to convert from f64"))?;
+        let uniform = Initializer::Uniform {
+            low: -limit,
+            high: limit,
+        };
+        self.initialize(uniform, rng);
         Ok(())
     }
 
@@ -89,14 +224,7 @@ impl<T: Float + Sized> Matrix2d<T> {
         self.columns == other_matrix.columns && self.rows == other_matrix.rows
     }
 
-    pub fn rows(&self) -> usize {
-        self.rows
-    }
-    pub fn columns(&self) -> usize {
-        self.columns
-    }
-
-    pub fn apply<F>(&self, fun: &F) -> Self
+    pub fn apply_serial<F>(&self, fun: &F) -> Self
     where
         F: Fn(&T) -> T,
     {
@@ -109,25 +237,155 @@ impl<T: Float + Sized> Matrix2d<T> {
         new
     }
 
-    pub fn dot(&self, rhs: &Self) -> Self {
-        if self.columns != rhs.rows {
-            panic!("Wrong size matrices size {} != {} self columns and rhs rows lenght must be the same",self.columns,rhs.rows);
+    pub fn try_dot_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product("dot", self.view(), rhs.view())
+    }
+    pub fn dot_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_serial(rhs))
+    }
+
+    /// `self.transpose().dot(rhs)` without materialising the transpose.
+    pub fn try_dot_tn_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product("dot_tn", self.t(), rhs.view())
+    }
+    pub fn dot_tn_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tn_serial(rhs))
+    }
+
+    /// `self.dot(&rhs.transpose())` without materialising the transpose.
+    pub fn try_dot_nt_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product("dot_nt", self.view(), rhs.view().t())
+    }
+    pub fn dot_nt_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_nt_serial(rhs))
+    }
+
+    /// `self.transpose().dot(&rhs.transpose())` without materialising either transpose.
+    pub fn try_dot_tt_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product("dot_tt", self.t(), rhs.view().t())
+    }
+    pub fn dot_tt_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tt_serial(rhs))
+    }
+
+    /// `a.dot(b)` into a new matrix. Transposed operands are just views with swapped strides,
+    /// the packing in `gemm` reads them in whatever order they are laid out.
+    fn product(operation: &'static str, a: MatrixView<T>, b: MatrixView<T>) -> Result<Self, ShapeError> {
+        let mut new = Self::new(a.rows(), b.columns());
+        new.prepare_gemm(operation, &a, &b, T::zero())?;
+        gemm::gemm(
+            a.rows(),
+            b.columns(),
+            a.columns(),
+            T::one(),
+            a.as_gemm_operand(),
+            b.as_gemm_operand(),
+            &mut new.inner,
+        );
+        Ok(new)
+    }
+
+    pub fn try_gemm_serial<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T) -> Result<(), ShapeError>
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        let (a, b) = (a.view(), b.view());
+        self.prepare_gemm("gemm", &a, &b, beta)?;
+        gemm::gemm(
+            a.rows(),
+            b.columns(),
+            a.columns(),
+            alpha,
+            a.as_gemm_operand(),
+            b.as_gemm_operand(),
+            &mut self.inner,
+        );
+        Ok(())
+    }
+    pub fn gemm_serial<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T)
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        or_panic(self.try_gemm_serial(alpha, a, b, beta))
+    }
+
+    fn prepare_gemm(
+        &mut self,
+        operation: &'static str,
+        a: &MatrixView<T>,
+        b: &MatrixView<T>,
+        beta: T,
+    ) -> Result<(), ShapeError> {
+        let (a_shape, b_shape) = ((a.rows(), a.columns()), (b.rows(), b.columns()));
+        if a.columns() != b.rows() {
+            return Err(ShapeError::new(operation, a_shape, b_shape));
+        }
+        if self.shape() != (a.rows(), b.columns()) {
+            return Err(ShapeError::new(operation, self.shape(), (a.rows(), b.columns())));
+        }
+        if beta.is_zero() {
+            self.fill(T::zero());
+        } else if beta != T::one() {
+            self.scale_mut_serial(beta);
         }
+        Ok(())
+    }
 
-        let mut new = Self::new(self.rows, rhs.columns);
-        for r1 in 0..self.rows {
-            for c2 in 0..rhs.columns {
-                let mut sum = T::zero();
-                for r2 in 0..rhs.rows {
-                    sum = sum + (self[r1][r2] * rhs[r2][c2]);
-                }
-                new[r1][c2] = sum;
-            }
+    pub fn try_axpy_serial(&mut self, a: T, x: &Self) -> Result<(), ShapeError> {
+        check_same_size(self, x, "axpy")?;
+        for (y, x) in self.inner.iter_mut().zip(&x.inner) {
+            *y = *y + a * (*x);
         }
-        new
+        Ok(())
+    }
+    pub fn axpy_serial(&mut self, a: T, x: &Self) {
+        or_panic(self.try_axpy_serial(a, x))
+    }
+
+    pub fn try_add_serial(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast(rhs, "add", |a, b| a + b)
+    }
+    pub fn try_sub_serial(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast(rhs, "sub", |a, b| a - b)
+    }
+    pub fn try_mul_serial(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast(rhs, "mul", |a, b| a * b)
+    }
+    pub fn try_div_serial(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast(rhs, "div", |a, b| a / b)
+    }
+
+    pub fn try_add_assign_serial(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign(rhs, "add_assign", |a, b| a + b)
+    }
+    pub fn try_sub_assign_serial(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign(rhs, "sub_assign", |a, b| a - b)
+    }
+    pub fn try_mul_assign_serial(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign(rhs, "mul_assign", |a, b| a * b)
+    }
+    pub fn try_div_assign_serial(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign(rhs, "div_assign", |a, b| a / b)
     }
 
-    pub fn scale(&self, n: T) -> Self {
+    pub fn apply_mut_serial<F>(&mut self, fun: &F)
+    where
+        F: Fn(&T) -> T,
+    {
+        for v in self.inner.iter_mut() {
+            *v = fun(v);
+        }
+    }
+
+    pub fn scale_mut_serial(&mut self, n: T) {
+        for v in self.inner.iter_mut() {
+            *v = (*v) * n;
+        }
+    }
+
+    pub fn scale_serial(&self, n: T) -> Self {
         let mut new = Self::new(self.rows, self.columns);
         for (i, v) in self.inner.iter().enumerate() {
             new.inner[i] = (*v) * n;
@@ -135,7 +393,7 @@ impl<T: Float + Sized> Matrix2d<T> {
         new
     }
 
-    pub fn add_scalar(&self, n: T) -> Self {
+    pub fn add_scalar_serial(&self, n: T) -> Self {
         let mut new = Self::new(self.rows, self.columns);
         for (i, v) in self.inner.iter().enumerate() {
             new.inner[i] = (*v) + n;
@@ -163,7 +421,7 @@ impl<T: Float + Send + Sync> Test for T {}
 impl<T: Test> Matrix2d<T> {
     pub fn apply_par<F>(&self, fun: &F) -> Self
     where
-        F: (Fn(&T) -> T) + Send + Sync + 'static,
+        F: (Fn(&T) -> T) + Send + Sync,
     {
         let mut new = self.clone();
 
@@ -171,31 +429,96 @@ impl<T: Test> Matrix2d<T> {
 
         new
     }
-    pub fn dot_par(&self, rhs: &Self) -> Self {
-        if self.columns != rhs.rows {
-            panic!("Wrong size matrices size {} != {} self columns and rhs rows lenght must be the same",self.columns,rhs.rows);
-        }
+    pub fn try_dot_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product_par("dot_par", self.view(), rhs.view())
+    }
+    pub fn dot_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_par(rhs))
+    }
 
-        let mut new = Self::new(self.rows, rhs.columns);
-
-        new.inner.par_iter_mut().enumerate().for_each(|(i, e)| {
-            let (x, y) = i_to_xy(i, new.columns);
-            let sum = (0..rhs.rows)
-                .into_par_iter()
-                .map(|r2| {
-                    //println!("x: {} y: {}  r2: {} self.rows: {} self.columns: {} rhs.rows: {} rhs.columns: {} i: {}",x,y,r2,self.rows,self.columns,rhs.rows,rhs.columns,i);
-                    return self[x][r2] * rhs[r2][y];
-                })
-                .reduce(
-                    || T::zero(),
-                    |x, y| {
-                        return x + y;
-                    },
-                );
-            *e = sum;
-        });
+    pub fn try_dot_tn_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product_par("dot_tn_par", self.t(), rhs.view())
+    }
+    pub fn dot_tn_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tn_par(rhs))
+    }
 
-        new
+    pub fn try_dot_nt_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product_par("dot_nt_par", self.view(), rhs.view().t())
+    }
+    pub fn dot_nt_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_nt_par(rhs))
+    }
+
+    pub fn try_dot_tt_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        Self::product_par("dot_tt_par", self.t(), rhs.view().t())
+    }
+    pub fn dot_tt_par<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tt_par(rhs))
+    }
+
+    fn product_par(operation: &'static str, a: MatrixView<T>, b: MatrixView<T>) -> Result<Self, ShapeError> {
+        let mut new = Self::new(a.rows(), b.columns());
+        new.prepare_gemm(operation, &a, &b, T::zero())?;
+        gemm::gemm_par(
+            a.rows(),
+            b.columns(),
+            a.columns(),
+            T::one(),
+            a.as_gemm_operand(),
+            b.as_gemm_operand(),
+            &mut new.inner,
+        );
+        Ok(new)
+    }
+
+    pub fn try_gemm_par<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T) -> Result<(), ShapeError>
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        let (a, b) = (a.view(), b.view());
+        self.prepare_gemm("gemm_par", &a, &b, beta)?;
+        gemm::gemm_par(
+            a.rows(),
+            b.columns(),
+            a.columns(),
+            alpha,
+            a.as_gemm_operand(),
+            b.as_gemm_operand(),
+            &mut self.inner,
+        );
+        Ok(())
+    }
+    pub fn gemm_par<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T)
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        or_panic(self.try_gemm_par(alpha, a, b, beta))
+    }
+
+    pub fn try_axpy_par(&mut self, a: T, x: &Self) -> Result<(), ShapeError> {
+        check_same_size(self, x, "axpy_par")?;
+        self.inner
+            .par_iter_mut()
+            .zip(x.inner.par_iter())
+            .for_each(|(y, x)| *y = *y + a * (*x));
+        Ok(())
+    }
+    pub fn axpy_par(&mut self, a: T, x: &Self) {
+        or_panic(self.try_axpy_par(a, x))
+    }
+
+    pub fn apply_mut_par<F>(&mut self, fun: &F)
+    where
+        F: (Fn(&T) -> T) + Send + Sync,
+    {
+        self.inner.par_iter_mut().for_each(|x| *x = fun(x));
+    }
+
+    pub fn scale_mut_par(&mut self, n: T) {
+        self.inner.par_iter_mut().for_each(|x| *x = (*x) * n);
     }
 
     pub fn scale_par(&self, n: T) -> Self {
@@ -223,29 +546,49 @@ impl<T: Test> Matrix2d<T> {
         new
     }
 
-    pub fn mul_par(mut self,rhs: &Self) -> Self{
-        panic_if_wrong_size(&self, &rhs);
-
-        self.inner.par_iter_mut().enumerate().for_each(|(i,x)|{
-            *x = (*x) * rhs.inner[i];
-
-        });
-        self
+    pub fn try_mul_par(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast_par(rhs, "mul_par", |a, b| a * b)
+    }
+    pub fn try_add_par(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast_par(rhs, "add_par", |a, b| a + b)
+    }
+    pub fn try_sub_par(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast_par(rhs, "sub_par", |a, b| a - b)
+    }
+    pub fn try_div_par(self, rhs: &Self) -> Result<Self, ShapeError> {
+        self.try_zip_broadcast_par(rhs, "div_par", |a, b| a / b)
     }
-    pub fn add_par(mut self,rhs: &Self) -> Self{
-        panic_if_wrong_size(&self, &rhs);
 
-        self.inner.par_iter_mut().enumerate().for_each(|(i,x)|{
-            *x = (*x) + rhs.inner[i];
+    pub fn try_add_assign_par(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign_par(rhs, "add_assign_par", |a, b| a + b)
+    }
+    pub fn try_sub_assign_par(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign_par(rhs, "sub_assign_par", |a, b| a - b)
+    }
+    pub fn try_mul_assign_par(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign_par(rhs, "mul_assign_par", |a, b| a * b)
+    }
+    pub fn try_div_assign_par(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        self.try_zip_assign_par(rhs, "div_assign_par", |a, b| a / b)
+    }
 
-        });
-        self
+    pub fn mul_par(self, rhs: &Self) -> Self {
+        or_panic(self.try_mul_par(rhs))
+    }
+    pub fn add_par(self, rhs: &Self) -> Self {
+        or_panic(self.try_add_par(rhs))
+    }
+    pub fn sub_par(self, rhs: &Self) -> Self {
+        or_panic(self.try_sub_par(rhs))
+    }
+    pub fn div_par(self, rhs: &Self) -> Self {
+        or_panic(self.try_div_par(rhs))
     }
 }
 
 impl<T: Float + ToString> Matrix2d<T> {
     pub fn save(&self, filename: &str) -> Result<()> {
-        let mut file = OpenOptions::new().create(true).write(true).open(filename)?;
+        let mut file = OpenOptions::new().create(true).write(true).truncate(true).open(filename)?;
         write!(file, "{}\n{}\n", self.rows, self.columns)?;
         write!(
             file,
@@ -281,95 +624,61 @@ impl<T: Float + ToString> Matrix2d<T> {
     }
 }
 
-impl<T: Float + ToString> Display for Matrix2d<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        dbg!(self.rows, self.columns);
-        let mut out = String::new();
-
-        for r in 0..self.rows {
-            if r != 0 {
-                out += " ";
-            }
-            for c in 0..self.columns {
-                let i = (r * self.columns) + c;
-                out += &self.inner[i].to_string();
-                if c != self.columns - 1 {
-                    out += ",";
-                }
-            }
-            if r != self.rows - 1 {
-                out += "\n";
-            }
-        }
-        write!(f, "[{}]", out)
-    }
-}
-
-impl<T: Float> Index<usize> for Matrix2d<T> {
+impl<T: Clone> Index<usize> for Matrix2d<T> {
     type Output = [T];
 
     fn index(&self, index: usize) -> &Self::Output {
-        return &self.inner[index * self.columns..(index + 1) * self.columns];
+        &self.inner[index * self.columns..(index + 1) * self.columns]
     }
 }
-impl<T: Float> IndexMut<usize> for Matrix2d<T> {
+impl<T: Clone> IndexMut<usize> for Matrix2d<T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        return &mut self.inner[index * self.columns..(index + 1) * self.columns];
+        &mut self.inner[index * self.columns..(index + 1) * self.columns]
     }
 }
 
-fn panic_if_wrong_size<T: Float>(m1: &Matrix2d<T>, m2: &Matrix2d<T>) {
-    if !m1.compare_dims(&m2) {
-        panic!(
-            "Matrices dimensions are not the same {} != {}, {} != {}",
-            m1.columns, m2.columns, m1.rows, m2.rows
-        );
+fn check_same_size<T: Float>(
+    m1: &Matrix2d<T>,
+    m2: &Matrix2d<T>,
+    operation: &'static str,
+) -> Result<(), ShapeError> {
+    if !m1.compare_dims(m2) {
+        return Err(ShapeError::new(operation, m1.shape(), m2.shape()));
     }
+    Ok(())
 }
 
-//TODO: check if correct
-impl<T: Float> Mul for Matrix2d<T> {
-    type Output = Self;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        panic_if_wrong_size(&self, &rhs);
-        //I hope compiler will optimize this
-        let mut new = Self::new(self.rows, rhs.columns);
+macro_rules! impl_op {
+    ($trait:ident, $method:ident, $try_method:ident) => {
+        impl<T: Float + Send + Sync> $trait for Matrix2d<T> {
+            type Output = Self;
 
-        for (i, v) in self.inner.iter().enumerate() {
-            let v2 = rhs.inner[i];
-            new.inner[i] = (*v) * v2;
+            fn $method(self, rhs: Self) -> Self::Output {
+                or_panic(self.$try_method(&rhs))
+            }
         }
-        new
-    }
+    };
 }
-
-impl<T: Float> Add for Matrix2d<T> {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        panic_if_wrong_size(&self, &rhs);
-        let mut new = Self::new(self.rows, self.columns);
-
-        for (i, v) in self.inner.iter().enumerate() {
-            let v2 = rhs.inner[i];
-            new.inner[i] = (*v) + v2;
+impl_op!(Add, add, try_add);
+impl_op!(Sub, sub, try_sub);
+impl_op!(Mul, mul, try_mul);
+impl_op!(Div, div, try_div);
+
+macro_rules! impl_assign_op {
+    ($trait:ident, $method:ident, $try_method:ident) => {
+        impl<T: Float + Send + Sync> $trait<&Matrix2d<T>> for Matrix2d<T> {
+            fn $method(&mut self, rhs: &Matrix2d<T>) {
+                or_panic(self.$try_method(rhs))
+            }
         }
-        new
-    }
-}
-
-impl<T: Float> Sub for Matrix2d<T> {
-    type Output = Self;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        panic_if_wrong_size(&self, &rhs);
-        let mut new = Self::new(self.rows, self.columns);
-
-        for (i, v) in self.inner.iter().enumerate() {
-            let v2 = rhs.inner[i];
-            new.inner[i] = (*v) - v2;
+        impl<T: Float + Send + Sync> $trait<Matrix2d<T>> for Matrix2d<T> {
+            fn $method(&mut self, rhs: Matrix2d<T>) {
+                or_panic(self.$try_method(&rhs))
+            }
         }
-        new
-    }
+    };
 }
+impl_assign_op!(AddAssign, add_assign, try_add_assign);
+impl_assign_op!(SubAssign, sub_assign, try_sub_assign);
+impl_assign_op!(MulAssign, mul_assign, try_mul_assign);
+impl_assign_op!(DivAssign, div_assign, try_div_assign);
@@ -0,0 +1,170 @@
+//! NumPy style broadcasting for elementwise operations.
+//!
+//! Two shapes are compatible when, for rows and for columns, the sizes are equal or
+//! one of them is 1. The size 1 side is repeated along that dimension.
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use super::{
+    error::{or_panic, ShapeError},
+    Matrix2d,
+};
+
+fn broadcast_dim(a: usize, b: usize) -> Option<usize> {
+    if a == b || b == 1 {
+        Some(a)
+    } else if a == 1 {
+        Some(b)
+    } else {
+        None
+    }
+}
+
+/// Resulting `(rows, columns)` of broadcasting two shapes, `None` when they are incompatible.
+pub fn broadcast_shape(a: (usize, usize), b: (usize, usize)) -> Option<(usize, usize)> {
+    Some((broadcast_dim(a.0, b.0)?, broadcast_dim(a.1, b.1)?))
+}
+
+pub(crate) fn check_broadcast<T: Clone, U: Clone>(
+    m1: &Matrix2d<T>,
+    m2: &Matrix2d<U>,
+    operation: &'static str,
+) -> Result<(usize, usize), ShapeError> {
+    broadcast_shape(m1.shape(), m2.shape()).ok_or(ShapeError::new(
+        operation,
+        m1.shape(),
+        m2.shape(),
+    ))
+}
+
+impl<T: Copy> Matrix2d<T> {
+    /// Element of the matrix seen through a broadcast to a larger shape.
+    #[inline(always)]
+    pub(super) fn broadcast_at(&self, r: usize, c: usize) -> T {
+        let r = if self.rows == 1 { 0 } else { r };
+        let c = if self.columns == 1 { 0 } else { c };
+        self.inner[r * self.columns + c]
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+
+    /// Repeats the matrix along its size 1 dimensions.
+    pub fn try_broadcast_to(&self, rows: usize, columns: usize) -> Result<Self, ShapeError> {
+        if broadcast_shape(self.shape(), (rows, columns)) != Some((rows, columns)) {
+            return Err(ShapeError::new(
+                "broadcast_to",
+                self.shape(),
+                (rows, columns),
+            ));
+        }
+        let mut new = Self::new(rows, columns);
+        for (i, v) in new.inner.iter_mut().enumerate() {
+            *v = self.broadcast_at(i / columns, i % columns);
+        }
+        Ok(new)
+    }
+    pub fn broadcast_to(&self, rows: usize, columns: usize) -> Self {
+        or_panic(self.try_broadcast_to(rows, columns))
+    }
+
+    /// Applies `fun` elementwise after broadcasting both operands, reusing `self` when it already has the output shape.
+    pub(crate) fn try_zip_broadcast<F>(
+        mut self,
+        rhs: &Self,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<Self, ShapeError>
+    where
+        F: Fn(T, T) -> T,
+    {
+        let (rows, columns) = check_broadcast(&self, rhs, operation)?;
+        if self.compare_dims(rhs) {
+            for (x, y) in self.inner.iter_mut().zip(&rhs.inner) {
+                *x = fun(*x, *y);
+            }
+            return Ok(self);
+        }
+        if self.shape() == (rows, columns) {
+            for (i, x) in self.inner.iter_mut().enumerate() {
+                *x = fun(*x, rhs.broadcast_at(i / columns, i % columns));
+            }
+            return Ok(self);
+        }
+        let mut new = Self::new(rows, columns);
+        for (i, x) in new.inner.iter_mut().enumerate() {
+            let (r, c) = (i / columns, i % columns);
+            *x = fun(self.broadcast_at(r, c), rhs.broadcast_at(r, c));
+        }
+        Ok(new)
+    }
+
+    /// In-place version of `try_zip_broadcast`, `rhs` has to broadcast to the shape of `self`.
+    pub(crate) fn try_zip_assign<F>(
+        &mut self,
+        rhs: &Self,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<(), ShapeError>
+    where
+        F: Fn(T, T) -> T,
+    {
+        let (rows, columns) = check_broadcast(self, rhs, operation)?;
+        if self.shape() != (rows, columns) {
+            return Err(ShapeError::new(operation, self.shape(), rhs.shape()));
+        }
+        for (i, x) in self.inner.iter_mut().enumerate() {
+            *x = fun(*x, rhs.broadcast_at(i / columns, i % columns));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    pub(crate) fn try_zip_broadcast_par<F>(
+        mut self,
+        rhs: &Self,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<Self, ShapeError>
+    where
+        F: Fn(T, T) -> T + Send + Sync,
+    {
+        let (rows, columns) = check_broadcast(&self, rhs, operation)?;
+        if self.shape() == (rows, columns) {
+            self.inner.par_iter_mut().enumerate().for_each(|(i, x)| {
+                *x = fun(*x, rhs.broadcast_at(i / columns, i % columns));
+            });
+            return Ok(self);
+        }
+        let mut new = Self::new(rows, columns);
+        new.inner.par_iter_mut().enumerate().for_each(|(i, x)| {
+            let (r, c) = (i / columns, i % columns);
+            *x = fun(self.broadcast_at(r, c), rhs.broadcast_at(r, c));
+        });
+        Ok(new)
+    }
+
+    pub(crate) fn try_zip_assign_par<F>(
+        &mut self,
+        rhs: &Self,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<(), ShapeError>
+    where
+        F: Fn(T, T) -> T + Send + Sync,
+    {
+        let (rows, columns) = check_broadcast(self, rhs, operation)?;
+        if self.shape() != (rows, columns) {
+            return Err(ShapeError::new(operation, self.shape(), rhs.shape()));
+        }
+        self.inner.par_iter_mut().enumerate().for_each(|(i, x)| {
+            *x = fun(*x, rhs.broadcast_at(i / columns, i % columns));
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! Serial or parallel execution chosen from the size of the work.
+//!
+//! The unsuffixed kernels (`dot`, `gemm`, `apply`, `scale`, the arithmetic operators, ...) run
+//! on rayon once their work, roughly the number of scalar operations, reaches
+//! `parallel_threshold()`, and serially below it where splitting into tasks costs more than it
+//! saves. The `_serial` and `_par` variants always run one way.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use num::Float;
+
+use super::{or_panic, AsView, Matrix2d, ShapeError};
+
+/// About 65 thousand scalar operations, a few tens of microseconds of serial work.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 1 << 16;
+
+static PARALLEL_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_PARALLEL_THRESHOLD);
+
+pub fn parallel_threshold() -> usize {
+    PARALLEL_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Sets the work from which the unsuffixed kernels run in parallel, for the whole process.
+/// `0` always parallelises and `usize::MAX` never does.
+pub fn set_parallel_threshold(work: usize) {
+    PARALLEL_THRESHOLD.store(work, Ordering::Relaxed);
+}
+
+/// Whether a kernel doing `work` scalar operations should run on rayon.
+#[cfg(feature = "rayon")]
+pub(crate) fn parallel(work: usize) -> bool {
+    work >= parallel_threshold() && rayon::current_num_threads() > 1
+}
+
+/// `$parallel` if `parallel($work)`, `$serial` otherwise. Without the `rayon` feature neither
+/// `$work` nor `$parallel` is compiled.
+macro_rules! par_if {
+    ($work:expr, $serial:expr, $parallel:expr) => {{
+        #[cfg(feature = "rayon")]
+        let result = if $crate::matrix::parallel($work) {
+            $parallel
+        } else {
+            $serial
+        };
+        #[cfg(not(feature = "rayon"))]
+        let result = $serial;
+        result
+    }};
+}
+pub(crate) use par_if;
+
+/// Elements of the broadcast result, which for an outer product is far more than either operand.
+/// Incompatible shapes count as no work, the serial kernel reports the error.
+#[cfg(feature = "rayon")]
+pub(crate) fn broadcast_work<T: Clone>(lhs: &Matrix2d<T>, rhs: &Matrix2d<T>) -> usize {
+    super::broadcast_shape(lhs.shape(), rhs.shape()).map_or(0, |(rows, columns)| rows * columns)
+}
+
+/// Reports errors under the name of the dispatching method, whichever kernel ran.
+fn renamed<R>(result: Result<R, ShapeError>, operation: &'static str) -> Result<R, ShapeError> {
+    result.map_err(|e| ShapeError { operation, ..e })
+}
+
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    pub fn apply<F>(&self, fun: &F) -> Self
+    where
+        F: (Fn(&T) -> T) + Send + Sync,
+    {
+        par_if!(
+            self.inner.len(),
+            self.apply_serial(fun),
+            self.apply_par(fun)
+        )
+    }
+    pub fn apply_mut<F>(&mut self, fun: &F)
+    where
+        F: (Fn(&T) -> T) + Send + Sync,
+    {
+        par_if!(
+            self.inner.len(),
+            self.apply_mut_serial(fun),
+            self.apply_mut_par(fun)
+        )
+    }
+
+    pub fn try_dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            self.inner.len() * rhs.view().columns(),
+            self.try_dot_serial(rhs),
+            self.try_dot_par(rhs)
+        );
+        renamed(result, "dot")
+    }
+    pub fn dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot(rhs))
+    }
+
+    pub fn try_dot_tn<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            self.inner.len() * rhs.view().columns(),
+            self.try_dot_tn_serial(rhs),
+            self.try_dot_tn_par(rhs)
+        );
+        renamed(result, "dot_tn")
+    }
+    pub fn dot_tn<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tn(rhs))
+    }
+
+    pub fn try_dot_nt<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            self.inner.len() * rhs.view().rows(),
+            self.try_dot_nt_serial(rhs),
+            self.try_dot_nt_par(rhs)
+        );
+        renamed(result, "dot_nt")
+    }
+    pub fn dot_nt<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_nt(rhs))
+    }
+
+    pub fn try_dot_tt<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            self.inner.len() * rhs.view().rows(),
+            self.try_dot_tt_serial(rhs),
+            self.try_dot_tt_par(rhs)
+        );
+        renamed(result, "dot_tt")
+    }
+    pub fn dot_tt<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Self {
+        or_panic(self.try_dot_tt(rhs))
+    }
+
+    /// Fused multiply-accumulate `self = alpha * a.dot(b) + beta * self`, written into the existing buffer.
+    /// Pass `m.t()` for a transposed operand, it is read in place without a copy.
+    pub fn try_gemm<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T) -> Result<(), ShapeError>
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        let result = par_if!(
+            a.view().rows() * a.view().columns() * b.view().columns(),
+            self.try_gemm_serial(alpha, a, b, beta),
+            self.try_gemm_par(alpha, a, b, beta)
+        );
+        renamed(result, "gemm")
+    }
+    pub fn gemm<A, B>(&mut self, alpha: T, a: &A, b: &B, beta: T)
+    where
+        A: AsView<T> + ?Sized,
+        B: AsView<T> + ?Sized,
+    {
+        or_panic(self.try_gemm(alpha, a, b, beta))
+    }
+
+    /// `self += a * x`
+    pub fn try_axpy(&mut self, a: T, x: &Self) -> Result<(), ShapeError> {
+        let result = par_if!(
+            self.inner.len(),
+            self.try_axpy_serial(a, x),
+            self.try_axpy_par(a, x)
+        );
+        renamed(result, "axpy")
+    }
+    pub fn axpy(&mut self, a: T, x: &Self) {
+        or_panic(self.try_axpy(a, x))
+    }
+
+    pub fn scale_mut(&mut self, n: T) {
+        par_if!(
+            self.inner.len(),
+            self.scale_mut_serial(n),
+            self.scale_mut_par(n)
+        )
+    }
+    pub fn scale(&self, n: T) -> Self {
+        par_if!(self.inner.len(), self.scale_serial(n), self.scale_par(n))
+    }
+    pub fn add_scalar(&self, n: T) -> Self {
+        par_if!(
+            self.inner.len(),
+            self.add_scalar_serial(n),
+            self.add_scalar_par(n)
+        )
+    }
+
+    pub fn try_add(self, rhs: &Self) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            broadcast_work(&self, rhs),
+            self.try_add_serial(rhs),
+            self.try_add_par(rhs)
+        );
+        renamed(result, "add")
+    }
+    pub fn try_sub(self, rhs: &Self) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            broadcast_work(&self, rhs),
+            self.try_sub_serial(rhs),
+            self.try_sub_par(rhs)
+        );
+        renamed(result, "sub")
+    }
+    pub fn try_mul(self, rhs: &Self) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            broadcast_work(&self, rhs),
+            self.try_mul_serial(rhs),
+            self.try_mul_par(rhs)
+        );
+        renamed(result, "mul")
+    }
+    pub fn try_div(self, rhs: &Self) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            broadcast_work(&self, rhs),
+            self.try_div_serial(rhs),
+            self.try_div_par(rhs)
+        );
+        renamed(result, "div")
+    }
+
+    pub fn try_add_assign(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        let result = par_if!(
+            self.inner.len(),
+            self.try_add_assign_serial(rhs),
+            self.try_add_assign_par(rhs)
+        );
+        renamed(result, "add_assign")
+    }
+    pub fn try_sub_assign(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        let result = par_if!(
+            self.inner.len(),
+            self.try_sub_assign_serial(rhs),
+            self.try_sub_assign_par(rhs)
+        );
+        renamed(result, "sub_assign")
+    }
+    pub fn try_mul_assign(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        let result = par_if!(
+            self.inner.len(),
+            self.try_mul_assign_serial(rhs),
+            self.try_mul_assign_par(rhs)
+        );
+        renamed(result, "mul_assign")
+    }
+    pub fn try_div_assign(&mut self, rhs: &Self) -> Result<(), ShapeError> {
+        let result = par_if!(
+            self.inner.len(),
+            self.try_div_assign_serial(rhs),
+            self.try_div_assign_par(rhs)
+        );
+        renamed(result, "div_assign")
+    }
+}
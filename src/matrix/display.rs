@@ -0,0 +1,78 @@
+//! `Display` for matrices, modelled on NumPy's array printing.
+//!
+//! A `rows x columns` header is followed by the elements in aligned columns. The precision
+//! (`{:.3}`) and minimum width (`{:8}`) of the format apply to every element. Matrices with
+//! more than `THRESHOLD` elements only show `EDGE_ITEMS` rows and columns at each end, the
+//! alternate form `{:#}` always prints everything.
+
+use std::fmt::{Display, Formatter, Result};
+
+use num::Float;
+
+use super::Matrix2d;
+
+const THRESHOLD: usize = 1000;
+const EDGE_ITEMS: usize = 3;
+const ELLIPSIS: &str = "...";
+
+/// Indices shown along a dimension of length `len`, `None` stands for the elided middle.
+fn shown(len: usize, truncate: bool) -> Vec<Option<usize>> {
+    if truncate && len > 2 * EDGE_ITEMS {
+        (0..EDGE_ITEMS)
+            .map(Some)
+            .chain([None])
+            .chain((len - EDGE_ITEMS..len).map(Some))
+            .collect()
+    } else {
+        (0..len).map(Some).collect()
+    }
+}
+
+impl<T: Float + Display> Display for Matrix2d<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let truncate = !f.alternate() && self.inner.len() > THRESHOLD;
+        let columns = shown(self.columns, truncate);
+        let rows: Vec<Option<Vec<String>>> = shown(self.rows, truncate)
+            .into_iter()
+            .map(|r| {
+                let r = r?;
+                let row = columns.iter().map(|c| match (c, f.precision()) {
+                    (None, _) => ELLIPSIS.to_string(),
+                    (Some(c), Some(precision)) => format!("{:.*}", precision, self[r][*c]),
+                    (Some(c), None) => self[r][*c].to_string(),
+                });
+                Some(row.collect())
+            })
+            .collect();
+        let width = rows
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|cell| cell.chars().count())
+            .chain(f.width())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(f, "{}x{} matrix", self.rows, self.columns)?;
+        write!(f, "[")?;
+        for (i, row) in rows.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",\n ")?;
+            }
+            match row {
+                None => write!(f, "{ELLIPSIS}")?,
+                Some(row) => {
+                    write!(f, "[")?;
+                    for (c, cell) in row.iter().enumerate() {
+                        if c != 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{cell:>width$}")?;
+                    }
+                    write!(f, "]")?;
+                }
+            }
+        }
+        write!(f, "]")
+    }
+}
@@ -0,0 +1,215 @@
+//! Iterative Jacobi methods: symmetric eigendecomposition and one-sided Jacobi SVD.
+
+use num::Float;
+
+use super::{error::ShapeError, linalg::LinalgError, Matrix2d};
+
+/// Stopping rule for the iterative decompositions.
+#[derive(Debug, Clone, Copy)]
+pub struct Convergence<T> {
+    /// Relative size of the off-diagonal part at which iteration stops.
+    pub tolerance: T,
+    /// Maximum number of full sweeps over all index pairs.
+    pub max_sweeps: usize,
+}
+
+impl<T: Float> Default for Convergence<T> {
+    fn default() -> Self {
+        Self {
+            tolerance: T::epsilon() * T::from(16.).unwrap(),
+            max_sweeps: 100,
+        }
+    }
+}
+
+/// `A = V diag(values) Vᵀ`, eigenvalues in descending order with the matching eigenvectors as columns of `vectors`.
+#[derive(Debug, Clone)]
+pub struct SymmetricEigen<T: Clone> {
+    pub values: Vec<T>,
+    pub vectors: Matrix2d<T>,
+    pub sweeps: usize,
+}
+
+/// Thin SVD `A = U diag(s) Vᵀ` with `k = min(rows, columns)` singular values in descending order.
+#[derive(Debug, Clone)]
+pub struct Svd<T: Clone> {
+    /// `rows x k`
+    pub u: Matrix2d<T>,
+    pub s: Vec<T>,
+    /// `k x columns`
+    pub vt: Matrix2d<T>,
+    pub sweeps: usize,
+}
+
+/// Rotation `(c, s)` that zeroes the off-diagonal entry of `[[app, apq], [apq, aqq]]`.
+fn jacobi_rotation<T: Float>(app: T, aqq: T, apq: T) -> (T, T) {
+    let two = T::one() + T::one();
+    let theta = (aqq - app) / (two * apq);
+    let t = theta.signum() / (theta.abs() + (theta * theta + T::one()).sqrt());
+    let c = T::one() / (t * t + T::one()).sqrt();
+    (c, t * c)
+}
+
+/// Replaces columns `p` and `q` of `m` with `c * p - s * q` and `s * p + c * q`.
+fn rotate_columns<T: Float>(m: &mut Matrix2d<T>, p: usize, q: usize, c: T, s: T) {
+    for r in 0..m.rows {
+        let (mp, mq) = (m[r][p], m[r][q]);
+        m[r][p] = c * mp - s * mq;
+        m[r][q] = s * mp + c * mq;
+    }
+}
+
+/// Order of indices that sorts `values` descending.
+fn descending<T: Float>(values: &[T]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| {
+        values[b]
+            .partial_cmp(&values[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order
+}
+
+fn select_columns<T: Float>(m: &Matrix2d<T>, order: &[usize]) -> Matrix2d<T> {
+    let mut new = Matrix2d::new(m.rows, order.len());
+    for r in 0..m.rows {
+        for (c, &o) in order.iter().enumerate() {
+            new[r][c] = m[r][o];
+        }
+    }
+    new
+}
+
+impl<T: Float> Matrix2d<T> {
+    /// Cyclic Jacobi eigendecomposition of a symmetric matrix.
+    pub fn symmetric_eigen(
+        &self,
+        convergence: Convergence<T>,
+    ) -> Result<SymmetricEigen<T>, LinalgError> {
+        let n = self.rows;
+        if self.rows != self.columns {
+            return Err(ShapeError::new("symmetric_eigen", self.shape(), (n, n)).into());
+        }
+        let norm = self.inner.iter().fold(T::zero(), |s, x| s + *x * *x).sqrt();
+        for r in 0..n {
+            for c in r + 1..n {
+                if (self[r][c] - self[c][r]).abs() > convergence.tolerance * norm {
+                    return Err(LinalgError::NotSymmetric);
+                }
+            }
+        }
+
+        let mut a = self.clone();
+        let mut v = Matrix2d::identity(n);
+
+        let mut sweeps = 0;
+        loop {
+            let off = (0..n)
+                .flat_map(|r| (0..n).filter(move |&c| c != r).map(move |c| (r, c)))
+                .fold(T::zero(), |s, (r, c)| s + a[r][c] * a[r][c])
+                .sqrt();
+            if off <= convergence.tolerance * norm {
+                break;
+            }
+            if sweeps >= convergence.max_sweeps {
+                return Err(LinalgError::NoConvergence { sweeps });
+            }
+            sweeps += 1;
+
+            for p in 0..n {
+                for q in p + 1..n {
+                    if a[p][q].is_zero() {
+                        continue;
+                    }
+                    let (c, s) = jacobi_rotation(a[p][p], a[q][q], a[p][q]);
+                    rotate_columns(&mut a, p, q, c, s);
+                    for k in 0..n {
+                        let (ap, aq) = (a[p][k], a[q][k]);
+                        a[p][k] = c * ap - s * aq;
+                        a[q][k] = s * ap + c * aq;
+                    }
+                    rotate_columns(&mut v, p, q, c, s);
+                }
+            }
+        }
+
+        let diagonal: Vec<T> = (0..n).map(|i| a[i][i]).collect();
+        let order = descending(&diagonal);
+        Ok(SymmetricEigen {
+            values: order.iter().map(|&i| diagonal[i]).collect(),
+            vectors: select_columns(&v, &order),
+            sweeps,
+        })
+    }
+
+    /// One-sided Jacobi SVD.
+    ///
+    /// Columns of `u` that belong to a zero singular value are left as zeros.
+    pub fn svd(&self, convergence: Convergence<T>) -> Result<Svd<T>, LinalgError> {
+        if self.rows < self.columns {
+            let svd = self.transpose().svd(convergence)?;
+            return Ok(Svd {
+                u: svd.vt.transpose(),
+                s: svd.s,
+                vt: svd.u.transpose(),
+                sweeps: svd.sweeps,
+            });
+        }
+
+        let n = self.columns;
+        let mut u = self.clone();
+        let mut v = Matrix2d::identity(n);
+
+        let column_dot = |m: &Matrix2d<T>, p: usize, q: usize| {
+            (0..m.rows).fold(T::zero(), |s, r| s + m[r][p] * m[r][q])
+        };
+
+        // A sweep only counts once it has to rotate, so a sweep that finds every pair of columns
+        // orthogonal is the convergence check, like the off-diagonal norm in `symmetric_eigen`.
+        let mut sweeps = 0;
+        loop {
+            let mut rotated = false;
+            for p in 0..n {
+                for q in p + 1..n {
+                    let alpha = column_dot(&u, p, p);
+                    let beta = column_dot(&u, q, q);
+                    let gamma = column_dot(&u, p, q);
+                    if gamma.abs() <= convergence.tolerance * (alpha * beta).sqrt() {
+                        continue;
+                    }
+                    if !rotated {
+                        if sweeps >= convergence.max_sweeps {
+                            return Err(LinalgError::NoConvergence { sweeps });
+                        }
+                        sweeps += 1;
+                        rotated = true;
+                    }
+                    let (c, s) = jacobi_rotation(alpha, beta, gamma);
+                    rotate_columns(&mut u, p, q, c, s);
+                    rotate_columns(&mut v, p, q, c, s);
+                }
+            }
+            if !rotated {
+                break;
+            }
+        }
+
+        let norms: Vec<T> = (0..n).map(|c| column_dot(&u, c, c).sqrt()).collect();
+        for r in 0..u.rows {
+            for (c, norm) in norms.iter().enumerate() {
+                u[r][c] = if norm.is_zero() {
+                    T::zero()
+                } else {
+                    u[r][c] / *norm
+                };
+            }
+        }
+        let order = descending(&norms);
+        Ok(Svd {
+            u: select_columns(&u, &order),
+            s: order.iter().map(|&i| norms[i]).collect(),
+            vt: select_columns(&v, &order).transpose(),
+            sweeps,
+        })
+    }
+}
@@ -0,0 +1,123 @@
+//! Elementwise math, so losses and optimizers can be written on whole matrices.
+//!
+//! Every unary operation `op` comes as `op` and `op_mut`, which pick serial or parallel
+//! execution like `apply`, plus `op_serial` and `op_par`. The binary `maximum` and `minimum`
+//! broadcast like `+`, they are not the `max` and `min` reductions.
+
+use num::Float;
+
+use super::{or_panic, Matrix2d, ShapeError};
+
+macro_rules! unary {
+    ($(#[$doc:meta])* $op:ident, $op_mut:ident, $op_serial:ident, $op_par:ident,
+        |$x:ident $(, $arg:ident: $ty:ty)*| $body:expr) => {
+        $(#[$doc])*
+        pub fn $op(&self $(, $arg: $ty)*) -> Self {
+            self.apply(&|$x: &T| {
+                let $x = *$x;
+                $body
+            })
+        }
+        pub fn $op_mut(&mut self $(, $arg: $ty)*) {
+            self.apply_mut(&|$x: &T| {
+                let $x = *$x;
+                $body
+            })
+        }
+        pub fn $op_serial(&self $(, $arg: $ty)*) -> Self {
+            self.apply_serial(&|$x: &T| {
+                let $x = *$x;
+                $body
+            })
+        }
+        #[cfg(feature = "rayon")]
+        pub fn $op_par(&self $(, $arg: $ty)*) -> Self {
+            self.apply_par(&|$x: &T| {
+                let $x = *$x;
+                $body
+            })
+        }
+    };
+}
+
+macro_rules! binary {
+    ($(#[$doc:meta])* $op:ident, $try_op:ident, $try_op_serial:ident, $op_par:ident,
+        $try_op_par:ident, $fun:expr) => {
+        $(#[$doc])*
+        pub fn $try_op(self, rhs: &Self) -> Result<Self, ShapeError> {
+            let result = super::par_if!(
+                super::dispatch::broadcast_work(&self, rhs),
+                self.$try_op_serial(rhs),
+                self.$try_op_par(rhs)
+            );
+            result.map_err(|e| ShapeError {
+                operation: stringify!($op),
+                ..e
+            })
+        }
+        pub fn $op(self, rhs: &Self) -> Self {
+            or_panic(self.$try_op(rhs))
+        }
+        pub fn $try_op_serial(self, rhs: &Self) -> Result<Self, ShapeError> {
+            self.try_zip_broadcast(rhs, stringify!($op), $fun)
+        }
+        #[cfg(feature = "rayon")]
+        pub fn $try_op_par(self, rhs: &Self) -> Result<Self, ShapeError> {
+            self.try_zip_broadcast_par(rhs, stringify!($op_par), $fun)
+        }
+        #[cfg(feature = "rayon")]
+        pub fn $op_par(self, rhs: &Self) -> Self {
+            or_panic(self.$try_op_par(rhs))
+        }
+    };
+}
+
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    unary!(exp, exp_mut, exp_serial, exp_par, |x| x.exp());
+    unary!(
+        /// Natural logarithm, `NaN` for negative and `-inf` for zero elements.
+        ln, ln_mut, ln_serial, ln_par, |x| x.ln()
+    );
+    unary!(sqrt, sqrt_mut, sqrt_serial, sqrt_par, |x| x.sqrt());
+    unary!(abs, abs_mut, abs_serial, abs_par, |x| x.abs());
+    unary!(signum, signum_mut, signum_serial, signum_par, |x| x.signum());
+    unary!(recip, recip_mut, recip_serial, recip_par, |x| x.recip());
+    unary!(tanh, tanh_mut, tanh_serial, tanh_par, |x| x.tanh());
+    unary!(powi, powi_mut, powi_serial, powi_par, |x, n: i32| x.powi(n));
+    unary!(powf, powf_mut, powf_serial, powf_par, |x, n: T| x.powf(n));
+    unary!(
+        /// Limits every element to `low..=high`, `NaN` stays `NaN`.
+        clamp, clamp_mut, clamp_serial, clamp_par, |x, low: T, high: T| {
+            if x < low {
+                low
+            } else if x > high {
+                high
+            } else {
+                x
+            }
+        }
+    );
+    unary!(
+        /// Divides every element by `n`, exactly rather than scaling by `1 / n`.
+        div_scalar, div_scalar_mut, div_scalar_serial, div_scalar_par, |x, n: T| x / n
+    );
+
+    binary!(
+        /// Elementwise larger of the two broadcast operands, ignoring `NaN` like `f64::max`.
+        maximum,
+        try_maximum,
+        try_maximum_serial,
+        maximum_par,
+        try_maximum_par,
+        |a: T, b: T| a.max(b)
+    );
+    binary!(
+        /// Elementwise smaller of the two broadcast operands, ignoring `NaN` like `f64::min`.
+        minimum,
+        try_minimum,
+        try_minimum_serial,
+        minimum_par,
+        try_minimum_par,
+        |a: T, b: T| a.min(b)
+    );
+}
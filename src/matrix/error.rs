@@ -0,0 +1,58 @@
+use std::{error::Error, fmt::Display};
+
+/// Returned by the `try_*` matrix operations when the operand shapes do not fit together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapeError {
+    pub operation: &'static str,
+    pub lhs: (usize, usize),
+    pub rhs: (usize, usize),
+}
+
+impl ShapeError {
+    pub fn new(operation: &'static str, lhs: (usize, usize), rhs: (usize, usize)) -> Self {
+        Self {
+            operation,
+            lhs,
+            rhs,
+        }
+    }
+}
+
+impl Display for ShapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: incompatible shapes {}x{} and {}x{}",
+            self.operation, self.lhs.0, self.lhs.1, self.rhs.0, self.rhs.1
+        )
+    }
+}
+
+impl Error for ShapeError {}
+
+/// Returned by the `try_*` operations that take row or column indices when one is past the end
+/// of its axis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexError {
+    pub operation: &'static str,
+    pub index: usize,
+    /// Number of rows or columns along the indexed axis.
+    pub len: usize,
+}
+
+impl Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: index {} is out of bounds for an axis of length {}",
+            self.operation, self.index, self.len
+        )
+    }
+}
+
+impl Error for IndexError {}
+
+/// Backs the panicking wrappers around the `try_*` operations.
+pub(crate) fn or_panic<R, E: Display>(result: Result<R, E>) -> R {
+    result.unwrap_or_else(|e| panic!("{}", e))
+}
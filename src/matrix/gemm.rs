@@ -0,0 +1,287 @@
+//! Cache blocked matrix multiply used by `Matrix2d::dot` and `Matrix2d::dot_par`.
+//!
+//! The product is computed GotoBLAS style: `B` is packed into `KC x NR` panels,
+//! `A` into `MR x KC` panels and a small register tile of `MR x NR` outputs is
+//! accumulated by the micro-kernel. `C` is always row-major and contiguous.
+//!
+//! Element types narrower than `f32` (`f16`, `bf16`) would lose most of their precision
+//! summing long dot products, they are widened to `f32` and rounded once at the end.
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+const MR: usize = 4;
+const NR: usize = 4;
+const MC: usize = 64;
+const KC: usize = 256;
+const NC: usize = 2048;
+
+/// Strided read-only operand, element `(r, c)` lives at `data[r * rs + c * cs]`.
+#[derive(Clone, Copy)]
+pub(crate) struct MatRef<'a, T> {
+    data: &'a [T],
+    rs: usize,
+    cs: usize,
+}
+
+impl<'a, T: Copy> MatRef<'a, T> {
+    pub(crate) fn new(data: &'a [T], rs: usize, cs: usize) -> Self {
+        Self { data, rs, cs }
+    }
+
+    #[inline(always)]
+    fn at(&self, r: usize, c: usize) -> T {
+        self.data[r * self.rs + c * self.cs]
+    }
+
+    #[cfg(feature = "rayon")]
+    fn skip_rows(&self, r: usize) -> Self {
+        Self {
+            data: &self.data[r * self.rs..],
+            rs: self.rs,
+            cs: self.cs,
+        }
+    }
+}
+
+/// `c += alpha * a * b` where `a` is `m x k`, `b` is `k x n` and `c` is a row-major `m x n` slice.
+pub(crate) fn gemm<T: Float>(
+    m: usize,
+    n: usize,
+    k: usize,
+    alpha: T,
+    a: MatRef<T>,
+    b: MatRef<T>,
+    c: &mut [T],
+) {
+    if m == 0 || n == 0 || k == 0 {
+        return;
+    }
+    if is_narrow::<T>() {
+        return widened(m, n, k, alpha, a, b, c, gemm::<f32>);
+    }
+    if n == 1 {
+        return gemv(m, k, alpha, a, b, c);
+    }
+    let mut a_pack = vec![T::zero(); MC.min(m).next_multiple_of(MR) * KC.min(k)];
+    let mut b_pack = vec![T::zero(); KC.min(k) * NC.min(n).next_multiple_of(NR)];
+
+    for jc in (0..n).step_by(NC) {
+        let nc = NC.min(n - jc);
+        for pc in (0..k).step_by(KC) {
+            let kc = KC.min(k - pc);
+            pack_b(&b, pc, jc, kc, nc, &mut b_pack);
+            for ic in (0..m).step_by(MC) {
+                let mc = MC.min(m - ic);
+                pack_a(&a, ic, pc, mc, kc, &mut a_pack);
+                macro_kernel(
+                    mc,
+                    nc,
+                    kc,
+                    alpha,
+                    &a_pack,
+                    &b_pack,
+                    &mut c[ic * n + jc..],
+                    n,
+                );
+            }
+        }
+    }
+}
+
+/// `gemm` or `gemm_par` for `f32`.
+type F32Gemm = fn(usize, usize, usize, f32, MatRef<f32>, MatRef<f32>, &mut [f32]);
+
+fn is_narrow<T>() -> bool {
+    std::mem::size_of::<T>() < std::mem::size_of::<f32>()
+}
+
+/// Runs `f32_gemm` on `f32` copies of the operands and rounds the result back into `c`.
+#[allow(clippy::too_many_arguments)]
+fn widened<T: Float>(
+    m: usize,
+    n: usize,
+    k: usize,
+    alpha: T,
+    a: MatRef<T>,
+    b: MatRef<T>,
+    c: &mut [T],
+    f32_gemm: F32Gemm,
+) {
+    let widen = |x: T| x.to_f32().unwrap();
+    let a_wide: Vec<f32> = (0..m * k).map(|i| widen(a.at(i / k, i % k))).collect();
+    let b_wide: Vec<f32> = (0..k * n).map(|i| widen(b.at(i / n, i % n))).collect();
+    let mut c_wide: Vec<f32> = c[..m * n].iter().map(|x| widen(*x)).collect();
+    f32_gemm(
+        m,
+        n,
+        k,
+        widen(alpha),
+        MatRef::new(&a_wide, k, 1),
+        MatRef::new(&b_wide, n, 1),
+        &mut c_wide,
+    );
+    for (c, x) in c.iter_mut().zip(c_wide) {
+        *c = T::from(x).unwrap();
+    }
+}
+
+/// Matrix-vector case, packing a single column of `b` into `NR` wide panels would waste most of the tile.
+fn gemv<T: Float>(m: usize, k: usize, alpha: T, a: MatRef<T>, b: MatRef<T>, c: &mut [T]) {
+    for (i, v) in c.iter_mut().enumerate().take(m) {
+        let mut sum = T::zero();
+        for p in 0..k {
+            sum = sum + a.at(i, p) * b.at(p, 0);
+        }
+        *v = *v + alpha * sum;
+    }
+}
+
+/// Parallel `c += alpha * a * b`.
+///
+/// Every `KC x NC` panel of `b` is packed once, in parallel, and shared by the rayon tasks, each
+/// of which packs and multiplies one horizontal block of `a` into its own rows of `c`.
+#[cfg(feature = "rayon")]
+pub(crate) fn gemm_par<T: Float + Send + Sync>(
+    m: usize,
+    n: usize,
+    k: usize,
+    alpha: T,
+    a: MatRef<T>,
+    b: MatRef<T>,
+    c: &mut [T],
+) {
+    if m == 0 || n == 0 || k == 0 {
+        return;
+    }
+    if is_narrow::<T>() {
+        return widened(m, n, k, alpha, a, b, c, gemm_par::<f32>);
+    }
+    let threads = rayon::current_num_threads();
+    let block_rows = m.div_ceil(threads).next_multiple_of(MR).min(MC);
+    if n == 1 {
+        return c[..m]
+            .par_chunks_mut(block_rows)
+            .enumerate()
+            .for_each(|(i, block)| {
+                gemv(block.len(), k, alpha, a.skip_rows(i * block_rows), b, block)
+            });
+    }
+    let mut b_pack = vec![T::zero(); KC.min(k) * NC.min(n).next_multiple_of(NR)];
+
+    for jc in (0..n).step_by(NC) {
+        let nc = NC.min(n - jc);
+        for pc in (0..k).step_by(KC) {
+            let kc = KC.min(k - pc);
+            b_pack[..kc * nc.next_multiple_of(NR)]
+                .par_chunks_mut(kc * NR)
+                .enumerate()
+                .for_each(|(panel, dst)| {
+                    let j0 = panel * NR;
+                    pack_b_panel(&b, pc, jc + j0, kc, NR.min(nc - j0), dst)
+                });
+            let b_pack = &b_pack;
+            c[..m * n]
+                .par_chunks_mut(block_rows * n)
+                .enumerate()
+                .for_each_init(
+                    || vec![T::zero(); block_rows * kc],
+                    |a_pack, (i, block)| {
+                        let mc = block.len() / n;
+                        pack_a(&a, i * block_rows, pc, mc, kc, a_pack);
+                        macro_kernel(mc, nc, kc, alpha, a_pack, b_pack, &mut block[jc..], n);
+                    },
+                );
+        }
+    }
+}
+
+/// Copies `b[pc..pc + kc, jc..jc + nc]` into column panels of width `NR`, zero padding the last one.
+fn pack_b<T: Float>(b: &MatRef<T>, pc: usize, jc: usize, kc: usize, nc: usize, out: &mut [T]) {
+    for (panel, j0) in (0..nc).step_by(NR).enumerate() {
+        let dst = &mut out[panel * kc * NR..(panel + 1) * kc * NR];
+        pack_b_panel(b, pc, jc + j0, kc, NR.min(nc - j0), dst);
+    }
+}
+
+/// Copies `b[pc..pc + kc, jc..jc + nr]` into one `kc x NR` panel.
+fn pack_b_panel<T: Float>(
+    b: &MatRef<T>,
+    pc: usize,
+    jc: usize,
+    kc: usize,
+    nr: usize,
+    dst: &mut [T],
+) {
+    for p in 0..kc {
+        for j in 0..NR {
+            dst[p * NR + j] = if j < nr {
+                b.at(pc + p, jc + j)
+            } else {
+                T::zero()
+            };
+        }
+    }
+}
+
+/// Copies `a[ic..ic + mc, pc..pc + kc]` into row panels of height `MR`, zero padding the last one.
+fn pack_a<T: Float>(a: &MatRef<T>, ic: usize, pc: usize, mc: usize, kc: usize, out: &mut [T]) {
+    for (panel, i0) in (0..mc).step_by(MR).enumerate() {
+        let mr = MR.min(mc - i0);
+        let dst = &mut out[panel * kc * MR..(panel + 1) * kc * MR];
+        for p in 0..kc {
+            for i in 0..MR {
+                dst[p * MR + i] = if i < mr {
+                    a.at(ic + i0 + i, pc + p)
+                } else {
+                    T::zero()
+                };
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn macro_kernel<T: Float>(
+    mc: usize,
+    nc: usize,
+    kc: usize,
+    alpha: T,
+    a_pack: &[T],
+    b_pack: &[T],
+    c: &mut [T],
+    ldc: usize,
+) {
+    for (jp, j0) in (0..nc).step_by(NR).enumerate() {
+        let nr = NR.min(nc - j0);
+        let b_panel = &b_pack[jp * kc * NR..(jp + 1) * kc * NR];
+        for (ip, i0) in (0..mc).step_by(MR).enumerate() {
+            let mr = MR.min(mc - i0);
+            let a_panel = &a_pack[ip * kc * MR..(ip + 1) * kc * MR];
+            let acc = micro_kernel(kc, a_panel, b_panel);
+            for i in 0..mr {
+                let row = &mut c[(i0 + i) * ldc + j0..(i0 + i) * ldc + j0 + nr];
+                for (j, v) in row.iter_mut().enumerate() {
+                    *v = *v + alpha * acc[i][j];
+                }
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn micro_kernel<T: Float>(kc: usize, a_panel: &[T], b_panel: &[T]) -> [[T; NR]; MR] {
+    let mut acc = [[T::zero(); NR]; MR];
+    let (a_panel, _) = a_panel.as_chunks::<MR>();
+    let (b_panel, _) = b_panel.as_chunks::<NR>();
+    for (a, b) in a_panel.iter().zip(b_panel).take(kc) {
+        for i in 0..MR {
+            for j in 0..NR {
+                acc[i][j] = acc[i][j] + a[i] * b[j];
+            }
+        }
+    }
+    acc
+}
@@ -0,0 +1,140 @@
+//! Random weight initialisation.
+//!
+//! For a weight matrix the fan-in is the number of columns (inputs) and the fan-out the
+//! number of rows (outputs), matching how `Network` multiplies `weights.dot(input)`.
+
+use std::f64::consts::PI;
+
+use num::Float;
+use rand::Rng;
+
+use super::Matrix2d;
+
+/// Distribution of the initial elements of a matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Initializer<T> {
+    /// Uniform in `low..high`.
+    Uniform {
+        low: T,
+        high: T,
+    },
+    Normal {
+        mean: T,
+        std_dev: T,
+    },
+    /// Normal, redrawn until within two standard deviations of the mean.
+    TruncatedNormal {
+        mean: T,
+        std_dev: T,
+    },
+    /// Glorot uniform, `±sqrt(6 / (fan_in + fan_out))`.
+    XavierUniform,
+    /// Glorot normal, standard deviation `sqrt(2 / (fan_in + fan_out))`.
+    XavierNormal,
+    /// Kaiming uniform for ReLU layers, `±sqrt(6 / fan_in)`.
+    HeUniform,
+    /// Kaiming normal for ReLU layers, standard deviation `sqrt(2 / fan_in)`.
+    HeNormal,
+    /// Orthonormal rows or columns, whichever there are fewer of, scaled by `gain`.
+    Orthogonal {
+        gain: T,
+    },
+    Constant(T),
+}
+
+fn uniform<R: Rng + ?Sized>(rng: &mut R, low: f64, high: f64) -> f64 {
+    low + (high - low) * rng.gen::<f64>()
+}
+
+/// Standard normal sample using the Box-Muller transform.
+fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    // `1 - gen` lies in (0, 1], keeping the logarithm finite.
+    let u1 = 1. - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+}
+
+fn truncated_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    loop {
+        let z = standard_normal(rng);
+        if z.abs() <= 2. {
+            return z;
+        }
+    }
+}
+
+impl<T: Float> Initializer<T> {
+    /// Draws a new `rows x columns` matrix.
+    pub fn sample<R: Rng + ?Sized>(&self, rows: usize, columns: usize, rng: &mut R) -> Matrix2d<T> {
+        let fan_in = columns as f64;
+        let fan_out = rows as f64;
+        let f = |x: T| x.to_f64().unwrap();
+        let uniform_in = |rng: &mut R, limit: f64| {
+            Matrix2d::from_fn(rows, columns, |_, _| {
+                T::from(uniform(rng, -limit, limit)).unwrap()
+            })
+        };
+        let normal = |rng: &mut R, mean: f64, std_dev: f64, draw: fn(&mut R) -> f64| {
+            Matrix2d::from_fn(rows, columns, |_, _| {
+                T::from(mean + std_dev * draw(rng)).unwrap()
+            })
+        };
+        match *self {
+            Initializer::Uniform { low, high } => Matrix2d::from_fn(rows, columns, |_, _| {
+                T::from(uniform(rng, f(low), f(high))).unwrap()
+            }),
+            Initializer::Normal { mean, std_dev } => {
+                normal(rng, f(mean), f(std_dev), standard_normal)
+            }
+            Initializer::TruncatedNormal { mean, std_dev } => {
+                normal(rng, f(mean), f(std_dev), truncated_normal)
+            }
+            Initializer::XavierUniform => uniform_in(rng, (6. / (fan_in + fan_out)).sqrt()),
+            Initializer::XavierNormal => {
+                normal(rng, 0., (2. / (fan_in + fan_out)).sqrt(), standard_normal)
+            }
+            Initializer::HeUniform => uniform_in(rng, (6. / fan_in).sqrt()),
+            Initializer::HeNormal => normal(rng, 0., (2. / fan_in).sqrt(), standard_normal),
+            Initializer::Orthogonal { gain } => orthogonal(rows, columns, gain, rng),
+            Initializer::Constant(value) => Matrix2d::full(rows, columns, value),
+        }
+    }
+}
+
+/// QR of a tall standard normal matrix, with the signs of `R`'s diagonal moved into `Q` so
+/// the result is uniformly distributed over the orthogonal matrices.
+fn orthogonal<T: Float, R: Rng + ?Sized>(
+    rows: usize,
+    columns: usize,
+    gain: T,
+    rng: &mut R,
+) -> Matrix2d<T> {
+    let (long, short) = (rows.max(columns), rows.min(columns));
+    let a = Matrix2d::from_fn(long, short, |_, _| T::from(standard_normal(rng)).unwrap());
+    let qr = a
+        .qr()
+        .expect("QR of a tall matrix cannot fail on its shape");
+    let mut q = qr.q().clone();
+    for c in 0..short {
+        let sign = if qr.r()[c][c] < T::zero() {
+            -gain
+        } else {
+            gain
+        };
+        for r in 0..long {
+            q[r][c] = q[r][c] * sign;
+        }
+    }
+    if rows < columns {
+        q.transpose()
+    } else {
+        q
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    /// Overwrites every element with a draw from `initializer`.
+    pub fn initialize<R: Rng + ?Sized>(&mut self, initializer: Initializer<T>, rng: &mut R) {
+        *self = initializer.sample(self.rows, self.columns, rng);
+    }
+}
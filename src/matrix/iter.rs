@@ -0,0 +1,125 @@
+//! Iteration over elements, rows and columns.
+//!
+//! Elements are always visited in row-major order.
+
+use std::{
+    iter::{FromIterator, StepBy},
+    slice,
+};
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+
+use super::{
+    error::{or_panic, ShapeError},
+    Matrix2d,
+};
+
+impl<T: Float> Matrix2d<T> {
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.inner.iter()
+    }
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.inner.iter_mut()
+    }
+    /// Every row as a contiguous slice.
+    pub fn rows_iter(&self) -> impl ExactSizeIterator<Item = &[T]> + '_ {
+        (0..self.rows).map(move |r| &self.inner[r * self.columns..(r + 1) * self.columns])
+    }
+    pub fn rows_iter_mut(&mut self) -> impl ExactSizeIterator<Item = &mut [T]> + '_ {
+        let columns = self.columns;
+        let rows = self.rows;
+        let mut rest = self.inner.as_mut_slice();
+        (0..rows).map(move |_| {
+            let (row, tail) = std::mem::take(&mut rest).split_at_mut(columns);
+            rest = tail;
+            row
+        })
+    }
+    /// Every column as an iterator striding through the row-major storage.
+    pub fn columns_iter(&self) -> impl ExactSizeIterator<Item = StepBy<slice::Iter<'_, T>>> + '_ {
+        (0..self.columns).map(move |c| {
+            let from_column: &[T] = self.inner.get(c..).unwrap_or(&[]);
+            from_column.iter().step_by(self.columns)
+        })
+    }
+    /// `(row, column, element)` for every element.
+    pub fn enumerate_indexed(&self) -> impl Iterator<Item = (usize, usize, &T)> + '_ {
+        self.inner
+            .iter()
+            .enumerate()
+            .map(move |(i, x)| (i / self.columns, i % self.columns, x))
+    }
+
+    /// Collects exactly `rows * columns` row-major elements.
+    pub fn try_from_shape_iter<I>(rows: usize, columns: usize, iter: I) -> Result<Self, ShapeError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::try_from_vec(rows, columns, iter.into_iter().collect()).map_err(|e| ShapeError {
+            operation: "from_shape_iter",
+            ..e
+        })
+    }
+    pub fn from_shape_iter<I>(rows: usize, columns: usize, iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        or_panic(Self::try_from_shape_iter(rows, columns, iter))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, T> {
+        self.inner.par_iter()
+    }
+    pub fn par_rows(&self) -> impl IndexedParallelIterator<Item = &[T]> + '_ {
+        (0..self.rows)
+            .into_par_iter()
+            .map(move |r| &self.inner[r * self.columns..(r + 1) * self.columns])
+    }
+}
+
+impl<T: Float> IntoIterator for Matrix2d<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<'a, T: Float> IntoIterator for &'a Matrix2d<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Float> IntoIterator for &'a mut Matrix2d<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Collects into an `n x 1` column, use `from_shape_iter` for any other shape.
+impl<T: Float> FromIterator<T> for Matrix2d<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let inner: Vec<T> = iter.into_iter().collect();
+        Self {
+            rows: inner.len(),
+            columns: 1,
+            inner,
+        }
+    }
+}
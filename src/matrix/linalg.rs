@@ -0,0 +1,368 @@
+//! Dense decompositions and solvers: LU with partial pivoting, Householder QR and Cholesky.
+
+use std::{cmp::Ordering, error::Error, fmt::Display};
+
+use num::Float;
+
+use super::{error::ShapeError, Matrix2d};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinalgError {
+    /// The operands do not have the shapes the operation needs, e.g. a non-square matrix for `lu`.
+    Shape(ShapeError),
+    /// A pivot is zero within the working precision.
+    Singular,
+    /// Cholesky hit a non-positive diagonal.
+    NotPositiveDefinite,
+    /// `symmetric_eigen` got a matrix that is not symmetric within the tolerance.
+    NotSymmetric,
+    /// An iterative method reached its sweep limit before converging.
+    NoConvergence { sweeps: usize },
+}
+
+impl Display for LinalgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinalgError::Shape(e) => write!(f, "{}", e),
+            LinalgError::Singular => write!(f, "matrix is singular to working precision"),
+            LinalgError::NotPositiveDefinite => write!(f, "matrix is not positive definite"),
+            LinalgError::NotSymmetric => write!(f, "matrix is not symmetric"),
+            LinalgError::NoConvergence { sweeps } => {
+                write!(f, "no convergence after {} sweeps", sweeps)
+            }
+        }
+    }
+}
+
+impl Error for LinalgError {}
+
+impl From<ShapeError> for LinalgError {
+    fn from(e: ShapeError) -> Self {
+        LinalgError::Shape(e)
+    }
+}
+
+fn check_square<T: Float>(m: &Matrix2d<T>, operation: &'static str) -> Result<usize, LinalgError> {
+    if m.rows != m.columns {
+        return Err(ShapeError::new(operation, m.shape(), (m.columns, m.columns)).into());
+    }
+    Ok(m.rows)
+}
+
+fn check_rhs<T: Float>(
+    n: usize,
+    b: &Matrix2d<T>,
+    operation: &'static str,
+) -> Result<(), LinalgError> {
+    if b.rows != n {
+        return Err(ShapeError::new(operation, (n, n), b.shape()).into());
+    }
+    Ok(())
+}
+
+/// Pivots at or below this magnitude are treated as zero.
+fn tolerance<T: Float>(m: &Matrix2d<T>) -> T {
+    let scale = m.inner.iter().fold(T::zero(), |a, b| a.max(b.abs()));
+    T::epsilon() * T::from(m.rows.max(m.columns)).unwrap() * scale
+}
+
+/// Solves `u x = b` in place for upper triangular `u`, reading only its first `n` rows and columns.
+fn back_substitute<T: Float>(u: &Matrix2d<T>, n: usize, b: &mut Matrix2d<T>) {
+    for c in 0..b.columns {
+        for i in (0..n).rev() {
+            let mut sum = b[i][c];
+            for k in i + 1..n {
+                sum = sum - u[i][k] * b[k][c];
+            }
+            b[i][c] = sum / u[i][i];
+        }
+    }
+}
+
+/// `P A = L U`, `L` has a unit diagonal and is stored below the diagonal of `lu`, `U` on and above it.
+#[derive(Debug, Clone)]
+pub struct Lu<T: Clone> {
+    lu: Matrix2d<T>,
+    pivots: Vec<usize>,
+    sign: T,
+    tolerance: T,
+}
+
+impl<T: Float> Lu<T> {
+    pub fn l(&self) -> Matrix2d<T> {
+        let mut l = Matrix2d::identity(self.lu.rows);
+        for r in 0..self.lu.rows {
+            l[r][..r].copy_from_slice(&self.lu[r][..r]);
+        }
+        l
+    }
+    pub fn u(&self) -> Matrix2d<T> {
+        let mut u = self.lu.clone();
+        for r in 0..u.rows {
+            u[r][..r].fill(T::zero());
+        }
+        u
+    }
+    /// Row `i` of `P A` is row `pivots()[i]` of `A`.
+    pub fn pivots(&self) -> &[usize] {
+        &self.pivots
+    }
+    pub fn p(&self) -> Matrix2d<T> {
+        let n = self.lu.rows;
+        let mut p = Matrix2d::zeros(n, n);
+        for (i, &j) in self.pivots.iter().enumerate() {
+            p[i][j] = T::one();
+        }
+        p
+    }
+
+    pub fn is_singular(&self) -> bool {
+        (0..self.lu.rows).any(|i| self.lu[i][i].abs() <= self.tolerance)
+    }
+
+    pub fn determinant(&self) -> T {
+        (0..self.lu.rows).fold(self.sign, |d, i| d * self.lu[i][i])
+    }
+
+    /// Solves `A x = b` for every column of `b`.
+    pub fn solve(&self, b: &Matrix2d<T>) -> Result<Matrix2d<T>, LinalgError> {
+        let n = self.lu.rows;
+        check_rhs(n, b, "lu_solve")?;
+        if self.is_singular() {
+            return Err(LinalgError::Singular);
+        }
+        let mut x = Matrix2d::new(n, b.columns);
+        for (i, &p) in self.pivots.iter().enumerate() {
+            x[i].copy_from_slice(&b[p]);
+        }
+        for c in 0..x.columns {
+            for i in 0..n {
+                let mut sum = x[i][c];
+                for k in 0..i {
+                    sum = sum - self.lu[i][k] * x[k][c];
+                }
+                x[i][c] = sum;
+            }
+        }
+        back_substitute(&self.lu, n, &mut x);
+        Ok(x)
+    }
+
+    pub fn inverse(&self) -> Result<Matrix2d<T>, LinalgError> {
+        self.solve(&Matrix2d::identity(self.lu.rows))
+    }
+}
+
+/// Thin QR, `A = Q R` with orthonormal columns in `Q` (`m x k`) and upper triangular `R` (`k x n`), `k = min(m, n)`.
+#[derive(Debug, Clone)]
+pub struct Qr<T: Clone> {
+    q: Matrix2d<T>,
+    r: Matrix2d<T>,
+    tolerance: T,
+}
+
+impl<T: Float> Qr<T> {
+    pub fn q(&self) -> &Matrix2d<T> {
+        &self.q
+    }
+    pub fn r(&self) -> &Matrix2d<T> {
+        &self.r
+    }
+
+    /// Least squares solution of `A x = b`, needs `A` to have at least as many rows as columns and full column rank.
+    pub fn solve(&self, b: &Matrix2d<T>) -> Result<Matrix2d<T>, LinalgError> {
+        let (m, n) = (self.q.rows, self.r.columns);
+        if m < n {
+            return Err(ShapeError::new("qr_solve", (m, n), b.shape()).into());
+        }
+        check_rhs(m, b, "qr_solve")?;
+        if (0..n).any(|i| self.r[i][i].abs() <= self.tolerance) {
+            return Err(LinalgError::Singular);
+        }
+        let mut x = self.q.t().dot(b);
+        back_substitute(&self.r, n, &mut x);
+        Ok(x)
+    }
+}
+
+/// `A = L Lᵀ` for a symmetric positive definite `A`, only the lower triangle of `A` is read.
+#[derive(Debug, Clone)]
+pub struct Cholesky<T: Clone> {
+    l: Matrix2d<T>,
+}
+
+impl<T: Float> Cholesky<T> {
+    pub fn l(&self) -> &Matrix2d<T> {
+        &self.l
+    }
+
+    pub fn determinant(&self) -> T {
+        (0..self.l.rows).fold(T::one(), |d, i| d * self.l[i][i] * self.l[i][i])
+    }
+
+    pub fn solve(&self, b: &Matrix2d<T>) -> Result<Matrix2d<T>, LinalgError> {
+        let n = self.l.rows;
+        check_rhs(n, b, "cholesky_solve")?;
+        let mut x = b.clone();
+        for c in 0..x.columns {
+            for i in 0..n {
+                let mut sum = x[i][c];
+                for k in 0..i {
+                    sum = sum - self.l[i][k] * x[k][c];
+                }
+                x[i][c] = sum / self.l[i][i];
+            }
+        }
+        back_substitute(&self.l.transpose(), n, &mut x);
+        Ok(x)
+    }
+
+    pub fn inverse(&self) -> Result<Matrix2d<T>, LinalgError> {
+        self.solve(&Matrix2d::identity(self.l.rows))
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    pub fn lu(&self) -> Result<Lu<T>, LinalgError> {
+        let n = check_square(self, "lu")?;
+        let mut lu = self.clone();
+        let mut pivots: Vec<usize> = (0..n).collect();
+        let mut sign = T::one();
+
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&a, &b| {
+                    lu[a][k]
+                        .abs()
+                        .partial_cmp(&lu[b][k].abs())
+                        .unwrap_or(Ordering::Equal)
+                })
+                .unwrap();
+            if p != k {
+                for c in 0..n {
+                    lu.inner.swap(p * n + c, k * n + c);
+                }
+                pivots.swap(p, k);
+                sign = -sign;
+            }
+            let pivot = lu[k][k];
+            if pivot.is_zero() {
+                continue;
+            }
+            for i in k + 1..n {
+                let factor = lu[i][k] / pivot;
+                lu[i][k] = factor;
+                for j in k + 1..n {
+                    lu[i][j] = lu[i][j] - factor * lu[k][j];
+                }
+            }
+        }
+
+        Ok(Lu {
+            lu,
+            pivots,
+            sign,
+            tolerance: tolerance(self),
+        })
+    }
+
+    pub fn qr(&self) -> Result<Qr<T>, LinalgError> {
+        let (m, n) = self.shape();
+        let k = m.min(n);
+        let mut a = self.clone();
+        let mut reflectors: Vec<Vec<T>> = Vec::with_capacity(k);
+
+        for j in 0..k {
+            let norm = (j..m).fold(T::zero(), |s, i| s + a[i][j] * a[i][j]).sqrt();
+            let alpha = if a[j][j] > T::zero() { -norm } else { norm };
+            let mut v: Vec<T> = (j..m).map(|i| a[i][j]).collect();
+            v[0] = v[0] - alpha;
+            let v_norm = v.iter().fold(T::zero(), |s, x| s + *x * *x);
+            if v_norm.is_zero() {
+                reflectors.push(Vec::new());
+                continue;
+            }
+            apply_reflector(&mut a, &v, v_norm, j, j);
+            reflectors.push(v);
+        }
+
+        let mut r = Matrix2d::zeros(k, n);
+        for i in 0..k {
+            r[i][i..].copy_from_slice(&a[i][i..]);
+        }
+
+        let mut q = Matrix2d::zeros(m, k);
+        for i in 0..k {
+            q[i][i] = T::one();
+        }
+        for (j, v) in reflectors.iter().enumerate().rev() {
+            if v.is_empty() {
+                continue;
+            }
+            let v_norm = v.iter().fold(T::zero(), |s, x| s + *x * *x);
+            apply_reflector(&mut q, v, v_norm, j, 0);
+        }
+
+        Ok(Qr {
+            q,
+            r,
+            tolerance: tolerance(self),
+        })
+    }
+
+    pub fn cholesky(&self) -> Result<Cholesky<T>, LinalgError> {
+        let n = check_square(self, "cholesky")?;
+        let mut l = Matrix2d::zeros(n, n);
+        for j in 0..n {
+            let mut d = self[j][j];
+            for k in 0..j {
+                d = d - l[j][k] * l[j][k];
+            }
+            if d <= T::zero() || d.is_nan() {
+                return Err(LinalgError::NotPositiveDefinite);
+            }
+            l[j][j] = d.sqrt();
+            for i in j + 1..n {
+                let mut s = self[i][j];
+                for k in 0..j {
+                    s = s - l[i][k] * l[j][k];
+                }
+                l[i][j] = s / l[j][j];
+            }
+        }
+        Ok(Cholesky { l })
+    }
+
+    pub fn determinant(&self) -> Result<T, LinalgError> {
+        Ok(self.lu()?.determinant())
+    }
+
+    pub fn inverse(&self) -> Result<Self, LinalgError> {
+        self.lu()?.inverse()
+    }
+
+    /// Solves `self x = b` for a square `self`.
+    pub fn solve(&self, b: &Self) -> Result<Self, LinalgError> {
+        self.lu()?.solve(b)
+    }
+
+    /// Least squares solution of `self x = b` through QR.
+    pub fn lstsq(&self, b: &Self) -> Result<Self, LinalgError> {
+        self.qr()?.solve(b)
+    }
+}
+
+/// Applies `I - 2 v vᵀ / (vᵀ v)` to rows `row..` of `a`, from column `column` on.
+fn apply_reflector<T: Float>(a: &mut Matrix2d<T>, v: &[T], v_norm: T, row: usize, column: usize) {
+    let two = T::one() + T::one();
+    for c in column..a.columns {
+        let dot = v
+            .iter()
+            .enumerate()
+            .fold(T::zero(), |s, (i, x)| s + *x * a[row + i][c]);
+        let factor = two * dot / v_norm;
+        for (i, x) in v.iter().enumerate() {
+            a[row + i][c] = a[row + i][c] - factor * *x;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Boolean masks for non-smooth layers: ReLU gradients, dropout, gradient clipping.
+//!
+//! Comparisons broadcast their operands like `+`. `where_` and `masked_fill` broadcast the
+//! smaller operands to the shape of the result, which never grows.
+
+use std::ops::Not;
+
+use num::Float;
+
+use super::{broadcast::check_broadcast, or_panic, Matrix2d, ShapeError};
+
+/// One `bool` per element, `true` where a condition holds.
+pub type Mask = Matrix2d<bool>;
+
+/// Checks that `m` broadcasts to `shape` without growing it.
+fn check_broadcast_to<U: Clone>(
+    m: &Matrix2d<U>,
+    shape: (usize, usize),
+    operation: &'static str,
+) -> Result<(), ShapeError> {
+    let fits = |n: usize, target: usize| n == target || n == 1;
+    if fits(m.rows, shape.0) && fits(m.columns, shape.1) {
+        Ok(())
+    } else {
+        Err(ShapeError::new(operation, shape, m.shape()))
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    fn try_compare<F>(
+        &self,
+        rhs: &Self,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<Mask, ShapeError>
+    where
+        F: Fn(T, T) -> bool,
+    {
+        let (rows, columns) = check_broadcast(self, rhs, operation)?;
+        let inner = (0..rows * columns)
+            .map(|i| {
+                let (r, c) = (i / columns, i % columns);
+                fun(self.broadcast_at(r, c), rhs.broadcast_at(r, c))
+            })
+            .collect();
+        Ok(Matrix2d {
+            inner,
+            rows,
+            columns,
+        })
+    }
+    fn compare_scalar<F: Fn(T) -> bool>(&self, fun: F) -> Mask {
+        Matrix2d {
+            inner: self.inner.iter().map(|x| fun(*x)).collect(),
+            rows: self.rows,
+            columns: self.columns,
+        }
+    }
+
+    pub fn try_gt(&self, rhs: &Self) -> Result<Mask, ShapeError> {
+        self.try_compare(rhs, "gt", |a, b| a > b)
+    }
+    pub fn gt(&self, rhs: &Self) -> Mask {
+        or_panic(self.try_gt(rhs))
+    }
+    pub fn try_lt(&self, rhs: &Self) -> Result<Mask, ShapeError> {
+        self.try_compare(rhs, "lt", |a, b| a < b)
+    }
+    pub fn lt(&self, rhs: &Self) -> Mask {
+        or_panic(self.try_lt(rhs))
+    }
+    /// `true` where the elements differ by at most `tolerance`, never for `NaN`.
+    pub fn try_eq_approx(&self, rhs: &Self, tolerance: T) -> Result<Mask, ShapeError> {
+        self.try_compare(rhs, "eq_approx", |a, b| (a - b).abs() <= tolerance)
+    }
+    pub fn eq_approx(&self, rhs: &Self, tolerance: T) -> Mask {
+        or_panic(self.try_eq_approx(rhs, tolerance))
+    }
+
+    pub fn gt_scalar(&self, n: T) -> Mask {
+        self.compare_scalar(|x| x > n)
+    }
+    pub fn lt_scalar(&self, n: T) -> Mask {
+        self.compare_scalar(|x| x < n)
+    }
+
+    /// Elements of `a` where `mask` is `true` and of `b` elsewhere, in the shape of `mask`.
+    pub fn try_where_(mask: &Mask, a: &Self, b: &Self) -> Result<Self, ShapeError> {
+        check_broadcast_to(a, mask.shape(), "where_")?;
+        check_broadcast_to(b, mask.shape(), "where_")?;
+        let columns = mask.columns;
+        let inner = mask
+            .inner
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let (r, c) = (i / columns, i % columns);
+                if *m {
+                    a.broadcast_at(r, c)
+                } else {
+                    b.broadcast_at(r, c)
+                }
+            })
+            .collect();
+        Ok(Self {
+            inner,
+            rows: mask.rows,
+            columns,
+        })
+    }
+    pub fn where_(mask: &Mask, a: &Self, b: &Self) -> Self {
+        or_panic(Self::try_where_(mask, a, b))
+    }
+
+    /// Sets the elements where `mask` is `true` to `value`, `mask` broadcasts to the shape of `self`.
+    pub fn try_masked_fill_mut(&mut self, mask: &Mask, value: T) -> Result<(), ShapeError> {
+        check_broadcast_to(mask, self.shape(), "masked_fill")?;
+        let columns = self.columns;
+        for (i, x) in self.inner.iter_mut().enumerate() {
+            if mask.broadcast_at(i / columns, i % columns) {
+                *x = value;
+            }
+        }
+        Ok(())
+    }
+    pub fn masked_fill_mut(&mut self, mask: &Mask, value: T) {
+        or_panic(self.try_masked_fill_mut(mask, value))
+    }
+    pub fn try_masked_fill(&self, mask: &Mask, value: T) -> Result<Self, ShapeError> {
+        let mut new = self.clone();
+        new.try_masked_fill_mut(mask, value)?;
+        Ok(new)
+    }
+    pub fn masked_fill(&self, mask: &Mask, value: T) -> Self {
+        or_panic(self.try_masked_fill(mask, value))
+    }
+}
+
+impl Mask {
+    /// Number of `true` elements.
+    pub fn count(&self) -> usize {
+        self.inner.iter().filter(|m| **m).count()
+    }
+    pub fn any(&self) -> bool {
+        self.inner.iter().any(|m| *m)
+    }
+    /// `true` for an empty mask.
+    pub fn all(&self) -> bool {
+        self.inner.iter().all(|m| *m)
+    }
+
+    /// `1` where the mask is `true` and `0` elsewhere, e.g. the derivative of ReLU.
+    pub fn to_float<T: Float>(&self) -> Matrix2d<T> {
+        Matrix2d {
+            inner: self
+                .inner
+                .iter()
+                .map(|m| if *m { T::one() } else { T::zero() })
+                .collect(),
+            rows: self.rows,
+            columns: self.columns,
+        }
+    }
+}
+
+impl Not for Mask {
+    type Output = Self;
+
+    fn not(mut self) -> Self {
+        self.inner.iter_mut().for_each(|m| *m = !*m);
+        self
+    }
+}
@@ -0,0 +1,352 @@
+//! NumPy `.npy` files, see <https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html>.
+//!
+//! Only 2 dimensional (and 0/1 dimensional, loaded as `1 x n`) arrays of `f16`, `f32` and `f64`
+//! are supported.
+
+use std::{
+    fs::OpenOptions,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Result};
+use num::Float;
+
+use super::Matrix2d;
+use crate::half::{bf16, f16};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NpyDtype {
+    F16,
+    /// Written with the `'bfloat16'` descr, which NumPy reads once `ml_dtypes` is imported.
+    /// Always little endian.
+    BF16,
+    F32,
+    F64,
+}
+
+impl NpyDtype {
+    /// The dtype that holds `T` without losing precision or range.
+    pub fn of<T: Float>() -> Self {
+        let size = std::mem::size_of::<T>();
+        if size <= 2 && T::max_value() <= T::from(f16::MAX).unwrap() {
+            NpyDtype::F16
+        } else if size <= 2 {
+            NpyDtype::BF16
+        } else if size <= 4 {
+            NpyDtype::F32
+        } else {
+            NpyDtype::F64
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            NpyDtype::F16 | NpyDtype::BF16 => 2,
+            NpyDtype::F32 => 4,
+            NpyDtype::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+}
+
+/// Element layout of a written `.npy` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NpyOptions {
+    pub dtype: NpyDtype,
+    pub endian: Endian,
+    /// Store the data column by column, as NumPy's `fortran_order`.
+    pub fortran_order: bool,
+}
+
+impl NpyOptions {
+    /// Little endian, C order and the dtype matching `T`.
+    pub fn of<T: Float>() -> Self {
+        Self {
+            dtype: NpyDtype::of::<T>(),
+            endian: Endian::Little,
+            fortran_order: false,
+        }
+    }
+
+    fn descr(&self) -> String {
+        if self.dtype == NpyDtype::BF16 {
+            return "bfloat16".to_string();
+        }
+        let endian = match self.endian {
+            Endian::Little => '<',
+            Endian::Big => '>',
+        };
+        format!("{endian}f{}", self.dtype.size())
+    }
+
+    fn parse_descr(descr: &str) -> Result<(NpyDtype, Endian)> {
+        let unsupported = || anyhow!("unsupported npy dtype {descr:?}");
+        if descr == "bfloat16" {
+            return Ok((NpyDtype::BF16, Endian::Little));
+        }
+        let mut chars = descr.chars();
+        let endian = match chars.next().ok_or_else(unsupported)? {
+            '<' => Endian::Little,
+            '>' => Endian::Big,
+            '=' => Endian::native(),
+            _ => return Err(unsupported()),
+        };
+        let dtype = match chars.as_str() {
+            "f2" => NpyDtype::F16,
+            "f4" => NpyDtype::F32,
+            "f8" => NpyDtype::F64,
+            _ => return Err(unsupported()),
+        };
+        Ok((dtype, endian))
+    }
+}
+
+/// Parsed header dictionary of a `.npy` file.
+struct Header {
+    options: NpyOptions,
+    shape: Vec<usize>,
+}
+
+impl Header {
+    fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        let shape = match self.shape.as_slice() {
+            [n] => format!("({n},)"),
+            dims => format!(
+                "({})",
+                dims.iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let fortran_order = if self.options.fortran_order {
+            "True"
+        } else {
+            "False"
+        };
+        let mut dict = format!(
+            "{{'descr': '{}', 'fortran_order': {fortran_order}, 'shape': {shape}, }}",
+            self.options.descr()
+        );
+        // Magic, version and length take 10 bytes, the whole header is padded to 64 bytes
+        // and ends with a newline.
+        let unpadded = MAGIC.len() + 4 + dict.len() + 1;
+        dict.extend(std::iter::repeat_n(
+            ' ',
+            unpadded.next_multiple_of(64) - unpadded,
+        ));
+        dict.push('\n');
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[1, 0])?;
+        w.write_all(&u16::try_from(dict.len())?.to_le_bytes())?;
+        w.write_all(dict.as_bytes())?;
+        Ok(())
+    }
+
+    fn read<R: Read>(r: &mut R) -> Result<Self> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic[..6] != MAGIC {
+            bail!("not a npy file");
+        }
+        let len = match magic[6] {
+            1 => {
+                let mut len = [0; 2];
+                r.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0; 4];
+                r.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            version => bail!("unsupported npy version {version}"),
+        };
+        let mut dict = vec![0; len];
+        r.read_exact(&mut dict)?;
+        let dict = String::from_utf8(dict)?;
+
+        let (dtype, endian) =
+            NpyOptions::parse_descr(dict_value(&dict, "descr")?.trim_matches('\''))?;
+        let fortran_order = match dict_value(&dict, "fortran_order")? {
+            "True" => true,
+            "False" => false,
+            other => bail!("invalid fortran_order {other:?}"),
+        };
+        let shape = dict_value(&dict, "shape")?
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(|d| d.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            options: NpyOptions {
+                dtype,
+                endian,
+                fortran_order,
+            },
+            shape,
+        })
+    }
+}
+
+/// Raw text of the value stored under `key` in the header dictionary.
+fn dict_value<'a>(dict: &'a str, key: &str) -> Result<&'a str> {
+    let missing = || anyhow!("npy header has no {key:?}");
+    let start = dict.find(&format!("'{key}'")).ok_or_else(missing)? + key.len() + 2;
+    let value = dict[start..]
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or_else(missing)?
+        .trim_start();
+    let end = if value.starts_with('(') {
+        value.find(')').map(|i| i + 1)
+    } else if let Some(quoted) = value.strip_prefix('\'') {
+        quoted.find('\'').map(|i| i + 2)
+    } else {
+        value.find([',', '}'])
+    };
+    Ok(&value[..end.ok_or_else(missing)?])
+}
+
+impl<T: Float> Matrix2d<T> {
+    /// Writes little endian, C ordered data of the dtype matching `T`.
+    pub fn save_npy<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save_npy_with(path, NpyOptions::of::<T>())
+    }
+    pub fn save_npy_with<P: AsRef<Path>>(&self, path: P, options: NpyOptions) -> Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        let mut w = BufWriter::new(file);
+        self.write_npy(&mut w, options)?;
+        w.flush()?;
+        Ok(())
+    }
+    /// Reads any supported dtype, byte order and memory order and converts the elements to `T`.
+    pub fn load_npy<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().read(true).open(path)?;
+        Self::read_npy(&mut BufReader::new(file))
+    }
+
+    pub fn write_npy<W: Write>(&self, w: &mut W, options: NpyOptions) -> Result<()> {
+        if options.dtype == NpyDtype::BF16 && options.endian == Endian::Big {
+            bail!("bfloat16 npy data is always little endian");
+        }
+        Header {
+            options,
+            shape: vec![self.rows, self.columns],
+        }
+        .write(w)?;
+
+        let mut bytes = Vec::with_capacity(self.inner.len() * options.dtype.size());
+        let mut push = |x: T| {
+            let x = x.to_f64().unwrap();
+            match (options.dtype, options.endian) {
+                (NpyDtype::F16, Endian::Little) => {
+                    bytes.extend(f16::from_f64(x).to_bits().to_le_bytes())
+                }
+                (NpyDtype::F16, Endian::Big) => {
+                    bytes.extend(f16::from_f64(x).to_bits().to_be_bytes())
+                }
+                (NpyDtype::BF16, _) => bytes.extend(bf16::from_f64(x).to_bits().to_le_bytes()),
+                (NpyDtype::F32, Endian::Little) => bytes.extend((x as f32).to_le_bytes()),
+                (NpyDtype::F32, Endian::Big) => bytes.extend((x as f32).to_be_bytes()),
+                (NpyDtype::F64, Endian::Little) => bytes.extend(x.to_le_bytes()),
+                (NpyDtype::F64, Endian::Big) => bytes.extend(x.to_be_bytes()),
+            }
+        };
+        if options.fortran_order {
+            for c in 0..self.columns {
+                for r in 0..self.rows {
+                    push(self[r][c]);
+                }
+            }
+        } else {
+            self.inner.iter().for_each(|x| push(*x));
+        }
+        w.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn read_npy<R: Read>(r: &mut R) -> Result<Self> {
+        let header = Header::read(r)?;
+        let (rows, columns) = match header.shape[..] {
+            [] => (1, 1),
+            [n] => (1, n),
+            [rows, columns] => (rows, columns),
+            _ => bail!("cannot load a {}-dimensional npy array", header.shape.len()),
+        };
+        let NpyOptions {
+            dtype,
+            endian,
+            fortran_order,
+        } = header.options;
+
+        // The header is untrusted, size the data without overflow and only allocate what the
+        // reader actually delivers.
+        let len = rows
+            .checked_mul(columns)
+            .and_then(|n| n.checked_mul(dtype.size()))
+            .ok_or_else(|| anyhow!("npy shape {rows}x{columns} is too large"))?;
+        let mut bytes = Vec::new();
+        (&mut *r).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            bail!(
+                "npy data is truncated, expected {len} bytes but found {}",
+                bytes.len()
+            );
+        }
+        let values = bytes.chunks_exact(dtype.size()).map(|b| {
+            let x = match (dtype, endian) {
+                (NpyDtype::F16, Endian::Little) => {
+                    f16::from_bits(u16::from_le_bytes(b.try_into().unwrap())).to_f64()
+                }
+                (NpyDtype::F16, Endian::Big) => {
+                    f16::from_bits(u16::from_be_bytes(b.try_into().unwrap())).to_f64()
+                }
+                (NpyDtype::BF16, _) => {
+                    bf16::from_bits(u16::from_le_bytes(b.try_into().unwrap())).to_f64()
+                }
+                (NpyDtype::F32, Endian::Little) => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+                (NpyDtype::F32, Endian::Big) => f32::from_be_bytes(b.try_into().unwrap()) as f64,
+                (NpyDtype::F64, Endian::Little) => f64::from_le_bytes(b.try_into().unwrap()),
+                (NpyDtype::F64, Endian::Big) => f64::from_be_bytes(b.try_into().unwrap()),
+            };
+            T::from(x).unwrap()
+        });
+
+        let mut new = Self::new(rows, columns);
+        if fortran_order {
+            for (i, x) in values.enumerate() {
+                new[i % rows][i / rows] = x;
+            }
+        } else {
+            new.inner = values.collect();
+        }
+        Ok(new)
+    }
+}
@@ -0,0 +1,192 @@
+//! NumPy `.npz` archives: a zip file with one `<name>.npy` entry per array.
+//!
+//! Entries are written uncompressed, as `numpy.savez` does. Reading supports stored
+//! entries only, archives from `numpy.savez_compressed` are rejected.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use num::Float;
+
+use super::{npy::NpyOptions, Matrix2d};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+/// 1980-01-01, the earliest date zip can store.
+const DOS_DATE: u16 = 0x21;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Little endian reads at an offset, failing instead of panicking on a truncated archive.
+struct Bytes<'a>(&'a [u8]);
+
+impl Bytes<'_> {
+    fn get<const N: usize>(&self, at: usize) -> Result<[u8; N]> {
+        self.slice(at, N)?
+            .try_into()
+            .map_err(|_| anyhow!("truncated npz archive"))
+    }
+    fn u16(&self, at: usize) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.get(at)?))
+    }
+    fn u32(&self, at: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.get(at)?))
+    }
+    fn u64(&self, at: usize) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.get(at)?))
+    }
+    fn slice(&self, at: usize, len: usize) -> Result<&[u8]> {
+        at.checked_add(len)
+            .and_then(|end| self.0.get(at..end))
+            .ok_or_else(|| anyhow!("truncated npz archive"))
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    /// Writes every matrix as `<name>.npy` with `NpyOptions::of::<T>()`, `numpy.load` gives them back by `name`.
+    pub fn save_npz<P: AsRef<Path>>(path: P, matrices: &[(&str, &Self)]) -> Result<()> {
+        let mut archive = Vec::new();
+        let mut central = Vec::new();
+        for (name, matrix) in matrices {
+            let name = format!("{name}.npy");
+            let mut data = Vec::new();
+            matrix.write_npy(&mut data, NpyOptions::of::<T>())?;
+            let crc = crc32(&data);
+            let size = u32::try_from(data.len())?;
+            let name_len = u16::try_from(name.len())?;
+            let offset = u32::try_from(archive.len())?;
+
+            archive.extend(LOCAL_HEADER.to_le_bytes());
+            for field in [20, 0, 0, 0, DOS_DATE] {
+                archive.extend(u16::to_le_bytes(field));
+            }
+            for field in [crc, size, size] {
+                archive.extend(field.to_le_bytes());
+            }
+            for field in [name_len, 0] {
+                archive.extend(field.to_le_bytes());
+            }
+            archive.extend(name.as_bytes());
+            archive.extend(&data);
+
+            central.extend(CENTRAL_HEADER.to_le_bytes());
+            for field in [20, 20, 0, 0, 0, DOS_DATE] {
+                central.extend(u16::to_le_bytes(field));
+            }
+            for field in [crc, size, size] {
+                central.extend(field.to_le_bytes());
+            }
+            for field in [name_len, 0, 0, 0, 0] {
+                central.extend(field.to_le_bytes());
+            }
+            for field in [0, offset] {
+                central.extend(u32::to_le_bytes(field));
+            }
+            central.extend(name.as_bytes());
+        }
+
+        let count = u16::try_from(matrices.len())?;
+        let central_offset = u32::try_from(archive.len())?;
+        let central_size = u32::try_from(central.len())?;
+        archive.extend(central);
+        archive.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        for field in [0, 0, count, count] {
+            archive.extend(u16::to_le_bytes(field));
+        }
+        for field in [central_size, central_offset] {
+            archive.extend(field.to_le_bytes());
+        }
+        archive.extend(0u16.to_le_bytes());
+
+        fs::write(path, archive)?;
+        Ok(())
+    }
+
+    /// Reads all arrays of the archive in stored order, named without the `.npy` suffix.
+    pub fn load_npz<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Self)>> {
+        let archive = fs::read(path)?;
+        let bytes = Bytes(&archive);
+
+        // The end of central directory record is followed by a comment of at most u16::MAX bytes.
+        let end = (0..=archive.len().saturating_sub(22))
+            .rev()
+            .take(u16::MAX as usize + 1)
+            .find(|&i| bytes.u32(i).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(|| anyhow!("not a npz archive"))?;
+        let count = bytes.u16(end + 10)? as usize;
+        let mut at = bytes.u32(end + 16)? as usize;
+
+        let mut matrices = Vec::with_capacity(count);
+        for _ in 0..count {
+            if bytes.u32(at)? != CENTRAL_HEADER {
+                bail!("corrupt npz central directory");
+            }
+            let method = bytes.u16(at + 10)?;
+            let mut size = bytes.u32(at + 20)? as u64;
+            let name_len = bytes.u16(at + 28)? as usize;
+            let extra_len = bytes.u16(at + 30)? as usize;
+            let comment_len = bytes.u16(at + 32)? as usize;
+            let mut offset = bytes.u32(at + 42)? as u64;
+            let name = String::from_utf8(bytes.slice(at + 46, name_len)?.to_vec())?;
+
+            // Zip64 fields are present, in this order, only for the values saturated in the header.
+            let mut extra = at + 46 + name_len;
+            let extra_end = extra + extra_len;
+            while extra + 4 <= extra_end {
+                let id = bytes.u16(extra)?;
+                let len = bytes.u16(extra + 2)? as usize;
+                if id == ZIP64_EXTRA {
+                    let mut field = extra + 4;
+                    if bytes.u32(at + 24)? == u32::MAX {
+                        field += 8;
+                    }
+                    if size == u32::MAX as u64 {
+                        size = bytes.u64(field)?;
+                        field += 8;
+                    }
+                    if offset == u32::MAX as u64 {
+                        offset = bytes.u64(field)?;
+                    }
+                }
+                extra += 4 + len;
+            }
+            at = extra_end + comment_len;
+
+            if method != 0 {
+                bail!("{name}: compressed npz entries are not supported");
+            }
+            // Offsets and sizes are untrusted, they must not overflow on the way to a bounds check.
+            let too_large = || anyhow!("{name}: npz entry is out of range");
+            let local = usize::try_from(offset).map_err(|_| too_large())?;
+            let size = usize::try_from(size).map_err(|_| too_large())?;
+            if bytes.u32(local)? != LOCAL_HEADER {
+                bail!("{name}: corrupt npz entry");
+            }
+            let name_len = bytes.u16(local.checked_add(26).ok_or_else(too_large)?)? as usize;
+            let extra_len = bytes.u16(local.checked_add(28).ok_or_else(too_large)?)? as usize;
+            let data_start = local
+                .checked_add(30 + name_len + extra_len)
+                .ok_or_else(too_large)?;
+            let mut data = bytes.slice(data_start, size)?;
+            let matrix = Self::read_npy(&mut data).map_err(|e| anyhow!("{name}: {e}"))?;
+            let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+            matrices.push((name, matrix));
+        }
+        Ok(matrices)
+    }
+}
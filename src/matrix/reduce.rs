@@ -0,0 +1,265 @@
+//! Reductions over the whole matrix or along an `Axis`.
+//!
+//! `Axis::Row` reduces every row to a single value and gives a `rows x 1` column,
+//! `Axis::Collumn` reduces every column and gives a `1 x columns` row.
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+
+use super::{Axis, Matrix2d};
+
+/// Strided walk over one row or one column of the matrix.
+#[derive(Clone, Copy)]
+struct Lane<'a, T> {
+    data: &'a [T],
+    start: usize,
+    stride: usize,
+    len: usize,
+}
+
+impl<'a, T: Copy> Lane<'a, T> {
+    fn iter(self) -> impl Iterator<Item = T> + 'a {
+        (0..self.len).map(move |i| self.data[self.start + i * self.stride])
+    }
+}
+
+fn sum<T: Float>(lane: Lane<T>) -> T {
+    lane.iter().fold(T::zero(), |a, b| a + b)
+}
+fn mean<T: Float>(lane: Lane<T>) -> T {
+    sum(lane) / T::from(lane.len).unwrap()
+}
+fn variance<T: Float>(lane: Lane<T>) -> T {
+    let mean = mean(lane);
+    lane.iter()
+        .fold(T::zero(), |a, b| a + (b - mean) * (b - mean))
+        / T::from(lane.len).unwrap()
+}
+fn max<T: Float>(lane: Lane<T>) -> T {
+    lane.iter().fold(T::neg_infinity(), T::max)
+}
+fn min<T: Float>(lane: Lane<T>) -> T {
+    lane.iter().fold(T::infinity(), T::min)
+}
+/// Index of the best element according to `better`, ties keep the first one and NaN never wins.
+fn arg_by<T: Float>(lane: Lane<T>, better: fn(T, T) -> bool) -> Option<usize> {
+    let mut values = lane.iter().enumerate();
+    let (mut best_index, mut best) = values.next()?;
+    for (i, v) in values {
+        if better(v, best) || (best.is_nan() && !v.is_nan()) {
+            best_index = i;
+            best = v;
+        }
+    }
+    Some(best_index)
+}
+fn argmax<T: Float>(lane: Lane<T>) -> Option<usize> {
+    arg_by(lane, |a, b| a > b)
+}
+fn argmin<T: Float>(lane: Lane<T>) -> Option<usize> {
+    arg_by(lane, |a, b| a < b)
+}
+
+impl<T: Float> Matrix2d<T> {
+    fn whole(&self) -> Lane<'_, T> {
+        Lane {
+            data: &self.inner,
+            start: 0,
+            stride: 1,
+            len: self.inner.len(),
+        }
+    }
+
+    fn lane_count(&self, axis: Axis) -> usize {
+        match axis {
+            Axis::Row => self.rows,
+            Axis::Collumn => self.columns,
+        }
+    }
+
+    fn lane(&self, axis: Axis, i: usize) -> Lane<'_, T> {
+        match axis {
+            Axis::Row => Lane {
+                data: &self.inner,
+                start: i * self.columns,
+                stride: 1,
+                len: self.columns,
+            },
+            Axis::Collumn => Lane {
+                data: &self.inner,
+                start: i,
+                stride: self.columns,
+                len: self.rows,
+            },
+        }
+    }
+
+    fn reduced(axis: Axis, inner: Vec<T>) -> Self {
+        let n = inner.len();
+        let (rows, columns) = match axis {
+            Axis::Row => (n, 1),
+            Axis::Collumn => (1, n),
+        };
+        Self {
+            inner,
+            rows,
+            columns,
+        }
+    }
+
+    fn reduce_axis(&self, axis: Axis, fun: fn(Lane<T>) -> T) -> Self {
+        let inner = (0..self.lane_count(axis))
+            .map(|i| fun(self.lane(axis, i)))
+            .collect();
+        Self::reduced(axis, inner)
+    }
+
+    fn arg_axis(&self, axis: Axis, fun: fn(Lane<T>) -> Option<usize>) -> Vec<Option<usize>> {
+        (0..self.lane_count(axis))
+            .map(|i| fun(self.lane(axis, i)))
+            .collect()
+    }
+
+    pub fn sum(&self) -> T {
+        sum(self.whole())
+    }
+    pub fn mean(&self) -> T {
+        mean(self.whole())
+    }
+    /// Population variance of all elements.
+    pub fn variance(&self) -> T {
+        variance(self.whole())
+    }
+    pub fn max(&self) -> T {
+        max(self.whole())
+    }
+    pub fn min(&self) -> T {
+        min(self.whole())
+    }
+    /// Row-major index of the largest element, `None` for an empty matrix.
+    pub fn argmax(&self) -> Option<usize> {
+        arg_by(self.whole(), |a, b| a > b)
+    }
+    /// Row-major index of the smallest element, `None` for an empty matrix.
+    pub fn argmin(&self) -> Option<usize> {
+        arg_by(self.whole(), |a, b| a < b)
+    }
+
+    pub fn sum_axis(&self, axis: Axis) -> Self {
+        self.reduce_axis(axis, sum)
+    }
+    pub fn mean_axis(&self, axis: Axis) -> Self {
+        self.reduce_axis(axis, mean)
+    }
+    pub fn variance_axis(&self, axis: Axis) -> Self {
+        self.reduce_axis(axis, variance)
+    }
+    pub fn max_axis(&self, axis: Axis) -> Self {
+        self.reduce_axis(axis, max)
+    }
+    pub fn min_axis(&self, axis: Axis) -> Self {
+        self.reduce_axis(axis, min)
+    }
+    /// Column index of the largest element of every row for `Axis::Row`, row index of every column for `Axis::Collumn`.
+    /// `None` for the lanes of a matrix with no columns or rows to choose from.
+    pub fn argmax_axis(&self, axis: Axis) -> Vec<Option<usize>> {
+        self.arg_axis(axis, argmax)
+    }
+    pub fn argmin_axis(&self, axis: Axis) -> Vec<Option<usize>> {
+        self.arg_axis(axis, argmin)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    fn reduce_axis_par(&self, axis: Axis, fun: fn(Lane<T>) -> T) -> Self {
+        let inner = (0..self.lane_count(axis))
+            .into_par_iter()
+            .map(|i| fun(self.lane(axis, i)))
+            .collect();
+        Self::reduced(axis, inner)
+    }
+
+    fn arg_axis_par(&self, axis: Axis, fun: fn(Lane<T>) -> Option<usize>) -> Vec<Option<usize>> {
+        (0..self.lane_count(axis))
+            .into_par_iter()
+            .map(|i| fun(self.lane(axis, i)))
+            .collect()
+    }
+
+    /// Parallel `arg_by` over all elements, the earlier index wins ties just like serially.
+    fn arg_by_par(&self, better: fn(T, T) -> bool) -> Option<usize> {
+        self.inner
+            .par_iter()
+            .copied()
+            .enumerate()
+            .map(Some)
+            .reduce(
+                || None,
+                |left, right| match (left, right) {
+                    (Some((_, l)), Some((_, r))) if better(r, l) || (l.is_nan() && !r.is_nan()) => {
+                        right
+                    }
+                    (None, _) => right,
+                    _ => left,
+                },
+            )
+            .map(|(i, _)| i)
+    }
+
+    pub fn sum_par(&self) -> T {
+        self.inner.par_iter().copied().reduce(T::zero, |a, b| a + b)
+    }
+    pub fn mean_par(&self) -> T {
+        self.sum_par() / T::from(self.inner.len()).unwrap()
+    }
+    pub fn variance_par(&self) -> T {
+        let mean = self.mean_par();
+        self.inner
+            .par_iter()
+            .map(|x| (*x - mean) * (*x - mean))
+            .reduce(T::zero, |a, b| a + b)
+            / T::from(self.inner.len()).unwrap()
+    }
+    pub fn max_par(&self) -> T {
+        self.inner
+            .par_iter()
+            .copied()
+            .reduce(T::neg_infinity, T::max)
+    }
+    pub fn min_par(&self) -> T {
+        self.inner.par_iter().copied().reduce(T::infinity, T::min)
+    }
+    pub fn argmax_par(&self) -> Option<usize> {
+        self.arg_by_par(|a, b| a > b)
+    }
+    pub fn argmin_par(&self) -> Option<usize> {
+        self.arg_by_par(|a, b| a < b)
+    }
+
+    pub fn sum_axis_par(&self, axis: Axis) -> Self {
+        self.reduce_axis_par(axis, sum)
+    }
+    pub fn mean_axis_par(&self, axis: Axis) -> Self {
+        self.reduce_axis_par(axis, mean)
+    }
+    pub fn variance_axis_par(&self, axis: Axis) -> Self {
+        self.reduce_axis_par(axis, variance)
+    }
+    pub fn max_axis_par(&self, axis: Axis) -> Self {
+        self.reduce_axis_par(axis, max)
+    }
+    pub fn min_axis_par(&self, axis: Axis) -> Self {
+        self.reduce_axis_par(axis, min)
+    }
+    pub fn argmax_axis_par(&self, axis: Axis) -> Vec<Option<usize>> {
+        self.arg_axis_par(axis, argmax)
+    }
+    pub fn argmin_axis_par(&self, axis: Axis) -> Vec<Option<usize>> {
+        self.arg_axis_par(axis, argmin)
+    }
+}
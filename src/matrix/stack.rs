@@ -0,0 +1,147 @@
+//! Joining matrices together and taking them apart along an `Axis`.
+//!
+//! `Axis::Row` works on whole rows, so concatenating stacks the matrices vertically,
+//! `Axis::Collumn` works on whole columns and stacks them horizontally.
+
+use num::Float;
+
+use super::{
+    error::{or_panic, IndexError, ShapeError},
+    Axis, Matrix2d,
+};
+
+impl<T: Float> Matrix2d<T> {
+    fn axis_len(&self, axis: Axis) -> usize {
+        match axis {
+            Axis::Row => self.rows,
+            Axis::Collumn => self.columns,
+        }
+    }
+
+    /// Rows (or columns) `range` of the matrix as a new matrix.
+    fn take_range(&self, axis: Axis, range: std::ops::Range<usize>) -> Self {
+        match axis {
+            Axis::Row => self.row_range(range).to_matrix(),
+            Axis::Collumn => self.column_range(range).to_matrix(),
+        }
+    }
+
+    /// Joins the matrices along `axis`, they must agree on the other dimension. Concatenating
+    /// nothing gives an empty `0x0` matrix.
+    pub fn try_concat(matrices: &[&Self], axis: Axis) -> Result<Self, ShapeError> {
+        let Some(first) = matrices.first() else {
+            return Ok(Self::zeros(0, 0));
+        };
+        let other = match axis {
+            Axis::Row => Axis::Collumn,
+            Axis::Collumn => Axis::Row,
+        };
+        if let Some(m) = matrices
+            .iter()
+            .find(|m| m.axis_len(other) != first.axis_len(other))
+        {
+            return Err(ShapeError::new("concat", first.shape(), m.shape()));
+        }
+
+        let len = matrices.iter().map(|m| m.axis_len(axis)).sum();
+        let mut inner = Vec::with_capacity(matrices.iter().map(|m| m.inner.len()).sum());
+        let (rows, columns) = match axis {
+            Axis::Row => {
+                for m in matrices {
+                    inner.extend_from_slice(&m.inner);
+                }
+                (len, first.columns)
+            }
+            Axis::Collumn => {
+                for r in 0..first.rows {
+                    for m in matrices {
+                        inner.extend_from_slice(&m[r]);
+                    }
+                }
+                (first.rows, len)
+            }
+        };
+        Ok(Self {
+            inner,
+            rows,
+            columns,
+        })
+    }
+    pub fn concat(matrices: &[&Self], axis: Axis) -> Self {
+        or_panic(Self::try_concat(matrices, axis))
+    }
+    /// Places the matrices side by side, same as `concat(matrices, Axis::Collumn)`.
+    pub fn try_hstack(matrices: &[&Self]) -> Result<Self, ShapeError> {
+        Self::try_concat(matrices, Axis::Collumn).map_err(|e| ShapeError {
+            operation: "hstack",
+            ..e
+        })
+    }
+    pub fn hstack(matrices: &[&Self]) -> Self {
+        or_panic(Self::try_hstack(matrices))
+    }
+    /// Places the matrices on top of each other, same as `concat(matrices, Axis::Row)`.
+    pub fn try_vstack(matrices: &[&Self]) -> Result<Self, ShapeError> {
+        Self::try_concat(matrices, Axis::Row).map_err(|e| ShapeError {
+            operation: "vstack",
+            ..e
+        })
+    }
+    pub fn vstack(matrices: &[&Self]) -> Self {
+        or_panic(Self::try_vstack(matrices))
+    }
+
+    /// The rows (or columns) before `index` and the ones from `index` on.
+    pub fn try_split_at(&self, axis: Axis, index: usize) -> Result<(Self, Self), IndexError> {
+        let len = self.axis_len(axis);
+        if index > len {
+            return Err(IndexError {
+                operation: "split_at",
+                index,
+                len,
+            });
+        }
+        Ok((
+            self.take_range(axis, 0..index),
+            self.take_range(axis, index..len),
+        ))
+    }
+    pub fn split_at(&self, axis: Axis, index: usize) -> (Self, Self) {
+        or_panic(self.try_split_at(axis, index))
+    }
+
+    /// Splits into pieces of `size` rows (or columns), the last one may be smaller.
+    ///
+    /// Panics if `size` is 0, like `slice::chunks`.
+    pub fn chunks(&self, axis: Axis, size: usize) -> Vec<Self> {
+        assert!(size != 0, "chunk size must be non-zero");
+        let len = self.axis_len(axis);
+        (0..len)
+            .step_by(size)
+            .map(|start| self.take_range(axis, start..(start + size).min(len)))
+            .collect()
+    }
+
+    /// Rows (or columns) at `indices`, in that order and possibly repeated.
+    pub fn try_gather(&self, axis: Axis, indices: &[usize]) -> Result<Self, IndexError> {
+        let len = self.axis_len(axis);
+        if let Some(&index) = indices.iter().find(|&&i| i >= len) {
+            return Err(IndexError {
+                operation: "gather",
+                index,
+                len,
+            });
+        }
+        Ok(match axis {
+            Axis::Row => Self::from_shape_iter(
+                indices.len(),
+                self.columns,
+                indices.iter().flat_map(|&r| self[r].iter().copied()),
+            ),
+            Axis::Collumn => Self::from_fn(self.rows, indices.len(), |r, c| self[r][indices[c]]),
+        })
+    }
+    pub fn gather(&self, axis: Axis, indices: &[usize]) -> Self {
+        or_panic(self.try_gather(axis, indices))
+    }
+}
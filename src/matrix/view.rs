@@ -0,0 +1,488 @@
+//! Borrowed, strided windows into a `Matrix2d`.
+//!
+//! A view keeps a row and a column stride, so sub-blocks, row or column ranges and
+//! transposes are all described without copying the underlying buffer.
+
+use std::ops::{Add, Bound, Div, Index, IndexMut, Mul, RangeBounds, Sub};
+
+use num::Float;
+
+use super::{
+    broadcast_shape,
+    error::{or_panic, ShapeError},
+    gemm,
+    gemm::MatRef,
+    Matrix2d,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct MatrixView<'a, T> {
+    data: &'a [T],
+    rows: usize,
+    columns: usize,
+    row_stride: usize,
+    column_stride: usize,
+}
+
+#[derive(Debug)]
+pub struct MatrixViewMut<'a, T> {
+    data: &'a mut [T],
+    rows: usize,
+    columns: usize,
+    row_stride: usize,
+    column_stride: usize,
+}
+
+/// Anything that can be read as a `MatrixView`, used by operations that accept both owned matrices and views.
+pub trait AsView<T> {
+    fn view(&self) -> MatrixView<'_, T>;
+}
+
+fn resolve(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+    if start > end || end > len {
+        panic!("Range {}..{} out of bounds for length {}", start, end, len);
+    }
+    (start, end)
+}
+
+/// Start offset and element count of the `rows x columns` block starting at `(r, c)`.
+fn block(
+    rows: usize,
+    columns: usize,
+    row_stride: usize,
+    column_stride: usize,
+    r: usize,
+    c: usize,
+) -> (usize, usize) {
+    if rows == 0 || columns == 0 {
+        return (0, 0);
+    }
+    let start = r * row_stride + c * column_stride;
+    let last = (r + rows - 1) * row_stride + (c + columns - 1) * column_stride;
+    (start, last + 1 - start)
+}
+
+impl<'a, T: Float> MatrixView<'a, T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> Option<&'a T> {
+        if r >= self.rows || c >= self.columns {
+            return None;
+        }
+        Some(&self.data[r * self.row_stride + c * self.column_stride])
+    }
+
+    /// Elements of row `r`, stepping by the column stride rather than indexing each one.
+    pub fn row_iter(&self, r: usize) -> impl Iterator<Item = &'a T> {
+        assert!(r < self.rows, "Row {} out of bounds for {} rows", r, self.rows);
+        let start = if self.columns == 0 {
+            self.data.len()
+        } else {
+            r * self.row_stride
+        };
+        self.data[start..]
+            .iter()
+            .step_by(self.column_stride.max(1))
+            .take(self.columns)
+    }
+
+    pub fn slice(
+        &self,
+        rows: impl RangeBounds<usize>,
+        columns: impl RangeBounds<usize>,
+    ) -> MatrixView<'a, T> {
+        let (r0, r1) = resolve(rows, self.rows);
+        let (c0, c1) = resolve(columns, self.columns);
+        let (start, len) = block(
+            r1 - r0,
+            c1 - c0,
+            self.row_stride,
+            self.column_stride,
+            r0,
+            c0,
+        );
+        MatrixView {
+            data: &self.data[start..start + len],
+            rows: r1 - r0,
+            columns: c1 - c0,
+            row_stride: self.row_stride,
+            column_stride: self.column_stride,
+        }
+    }
+    pub fn row_range(&self, rows: impl RangeBounds<usize>) -> MatrixView<'a, T> {
+        self.slice(rows, ..)
+    }
+    pub fn column_range(&self, columns: impl RangeBounds<usize>) -> MatrixView<'a, T> {
+        self.slice(.., columns)
+    }
+
+    /// Transposed view, swaps the strides.
+    pub fn t(&self) -> MatrixView<'a, T> {
+        MatrixView {
+            data: self.data,
+            rows: self.columns,
+            columns: self.rows,
+            row_stride: self.column_stride,
+            column_stride: self.row_stride,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix2d<T> {
+        let mut inner = Vec::with_capacity(self.rows * self.columns);
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                inner.push(self[(r, c)]);
+            }
+        }
+        Matrix2d {
+            inner,
+            rows: self.rows,
+            columns: self.columns,
+        }
+    }
+
+    pub(crate) fn as_gemm_operand(&self) -> MatRef<'a, T> {
+        MatRef::new(self.data, self.row_stride, self.column_stride)
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.columns)
+    }
+
+    pub fn try_dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Matrix2d<T>, ShapeError> {
+        let rhs = rhs.view();
+        if self.columns != rhs.rows {
+            return Err(ShapeError::new("dot", self.shape(), rhs.shape()));
+        }
+        let mut new = Matrix2d::zeros(self.rows, rhs.columns);
+        gemm::gemm(
+            self.rows,
+            rhs.columns,
+            self.columns,
+            T::one(),
+            self.as_gemm_operand(),
+            rhs.as_gemm_operand(),
+            &mut new.inner,
+        );
+        Ok(new)
+    }
+    pub fn dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Matrix2d<T> {
+        or_panic(self.try_dot(rhs))
+    }
+
+    /// Element of the view seen through a broadcast to a larger shape.
+    fn broadcast_at(&self, r: usize, c: usize) -> T {
+        let r = if self.rows == 1 { 0 } else { r };
+        let c = if self.columns == 1 { 0 } else { c };
+        self[(r, c)]
+    }
+
+    /// Applies `fun` elementwise after broadcasting both views, like the owned matrix operators.
+    fn try_zip_with<F: Fn(T, T) -> T>(
+        &self,
+        rhs: &MatrixView<T>,
+        operation: &'static str,
+        fun: F,
+    ) -> Result<Matrix2d<T>, ShapeError> {
+        let (rows, columns) = broadcast_shape(self.shape(), rhs.shape())
+            .ok_or(ShapeError::new(operation, self.shape(), rhs.shape()))?;
+        let mut inner = Vec::with_capacity(rows * columns);
+        for r in 0..rows {
+            for c in 0..columns {
+                inner.push(fun(self.broadcast_at(r, c), rhs.broadcast_at(r, c)));
+            }
+        }
+        Ok(Matrix2d {
+            inner,
+            rows,
+            columns,
+        })
+    }
+
+    pub fn try_add(&self, rhs: &MatrixView<T>) -> Result<Matrix2d<T>, ShapeError> {
+        self.try_zip_with(rhs, "add", |a, b| a + b)
+    }
+    pub fn try_sub(&self, rhs: &MatrixView<T>) -> Result<Matrix2d<T>, ShapeError> {
+        self.try_zip_with(rhs, "sub", |a, b| a - b)
+    }
+    pub fn try_mul(&self, rhs: &MatrixView<T>) -> Result<Matrix2d<T>, ShapeError> {
+        self.try_zip_with(rhs, "mul", |a, b| a * b)
+    }
+    pub fn try_div(&self, rhs: &MatrixView<T>) -> Result<Matrix2d<T>, ShapeError> {
+        self.try_zip_with(rhs, "div", |a, b| a / b)
+    }
+
+    fn fold<F: Fn(T, T) -> T>(&self, init: T, fun: F) -> T {
+        let mut acc = init;
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                acc = fun(acc, self[(r, c)]);
+            }
+        }
+        acc
+    }
+
+    pub fn sum(&self) -> T {
+        self.fold(T::zero(), |a, b| a + b)
+    }
+    pub fn max(&self) -> T {
+        self.fold(T::neg_infinity(), T::max)
+    }
+    pub fn min(&self) -> T {
+        self.fold(T::infinity(), T::min)
+    }
+}
+
+impl<'a, T: Float> MatrixViewMut<'a, T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get_mut(&mut self, r: usize, c: usize) -> Option<&mut T> {
+        if r >= self.rows || c >= self.columns {
+            return None;
+        }
+        Some(&mut self.data[r * self.row_stride + c * self.column_stride])
+    }
+
+    pub fn slice_mut(
+        &mut self,
+        rows: impl RangeBounds<usize>,
+        columns: impl RangeBounds<usize>,
+    ) -> MatrixViewMut<'_, T> {
+        let (r0, r1) = resolve(rows, self.rows);
+        let (c0, c1) = resolve(columns, self.columns);
+        let (start, len) = block(
+            r1 - r0,
+            c1 - c0,
+            self.row_stride,
+            self.column_stride,
+            r0,
+            c0,
+        );
+        MatrixViewMut {
+            data: &mut self.data[start..start + len],
+            rows: r1 - r0,
+            columns: c1 - c0,
+            row_stride: self.row_stride,
+            column_stride: self.column_stride,
+        }
+    }
+
+    pub fn t_mut(&mut self) -> MatrixViewMut<'_, T> {
+        MatrixViewMut {
+            data: &mut *self.data,
+            rows: self.columns,
+            columns: self.rows,
+            row_stride: self.column_stride,
+            column_stride: self.row_stride,
+        }
+    }
+
+    pub fn fill(&mut self, n: T) {
+        self.apply_mut(|_| n);
+    }
+
+    pub fn apply_mut<F: Fn(T) -> T>(&mut self, fun: F) {
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                let v = &mut self[(r, c)];
+                *v = fun(*v);
+            }
+        }
+    }
+
+    /// Copies `src` into the viewed elements.
+    pub fn try_assign<R: AsView<T> + ?Sized>(&mut self, src: &R) -> Result<(), ShapeError> {
+        let src = src.view();
+        if (self.rows, self.columns) != src.shape() {
+            return Err(ShapeError::new(
+                "assign",
+                (self.rows, self.columns),
+                src.shape(),
+            ));
+        }
+        for r in 0..self.rows {
+            for c in 0..self.columns {
+                self[(r, c)] = src[(r, c)];
+            }
+        }
+        Ok(())
+    }
+    pub fn assign<R: AsView<T> + ?Sized>(&mut self, src: &R) {
+        or_panic(self.try_assign(src))
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    pub fn view_mut(&mut self) -> MatrixViewMut<'_, T> {
+        MatrixViewMut {
+            data: &mut self.inner,
+            rows: self.rows,
+            columns: self.columns,
+            row_stride: self.columns,
+            column_stride: 1,
+        }
+    }
+
+    pub fn slice(
+        &self,
+        rows: impl RangeBounds<usize>,
+        columns: impl RangeBounds<usize>,
+    ) -> MatrixView<'_, T> {
+        self.view().slice(rows, columns)
+    }
+    pub fn slice_mut(
+        &mut self,
+        rows: impl RangeBounds<usize>,
+        columns: impl RangeBounds<usize>,
+    ) -> MatrixViewMut<'_, T> {
+        let (r0, r1) = resolve(rows, self.rows);
+        let (c0, c1) = resolve(columns, self.columns);
+        let (start, len) = block(r1 - r0, c1 - c0, self.columns, 1, r0, c0);
+        MatrixViewMut {
+            data: &mut self.inner[start..start + len],
+            rows: r1 - r0,
+            columns: c1 - c0,
+            row_stride: self.columns,
+            column_stride: 1,
+        }
+    }
+    pub fn row_range(&self, rows: impl RangeBounds<usize>) -> MatrixView<'_, T> {
+        self.view().row_range(rows)
+    }
+    pub fn column_range(&self, columns: impl RangeBounds<usize>) -> MatrixView<'_, T> {
+        self.view().column_range(columns)
+    }
+
+    /// Transposed view of the matrix, unlike `transpose` nothing is copied.
+    pub fn t(&self) -> MatrixView<'_, T> {
+        self.view().t()
+    }
+}
+
+impl<T: Clone> AsView<T> for Matrix2d<T> {
+    fn view(&self) -> MatrixView<'_, T> {
+        MatrixView {
+            data: &self.inner,
+            rows: self.rows,
+            columns: self.columns,
+            row_stride: self.columns,
+            column_stride: 1,
+        }
+    }
+}
+impl<T> AsView<T> for MatrixView<'_, T> {
+    fn view(&self) -> MatrixView<'_, T> {
+        MatrixView { ..*self }
+    }
+}
+impl<T> AsView<T> for MatrixViewMut<'_, T> {
+    fn view(&self) -> MatrixView<'_, T> {
+        MatrixView {
+            data: self.data,
+            rows: self.rows,
+            columns: self.columns,
+            row_stride: self.row_stride,
+            column_stride: self.column_stride,
+        }
+    }
+}
+
+impl<T: Float> Index<(usize, usize)> for MatrixView<'_, T> {
+    type Output = T;
+
+    fn index(&self, (r, c): (usize, usize)) -> &Self::Output {
+        match self.get(r, c) {
+            Some(v) => v,
+            None => panic!(
+                "Index ({}, {}) out of bounds for {}x{} view",
+                r, c, self.rows, self.columns
+            ),
+        }
+    }
+}
+impl<T: Float> Index<(usize, usize)> for MatrixViewMut<'_, T> {
+    type Output = T;
+
+    fn index(&self, (r, c): (usize, usize)) -> &Self::Output {
+        if r >= self.rows || c >= self.columns {
+            panic!(
+                "Index ({}, {}) out of bounds for {}x{} view",
+                r, c, self.rows, self.columns
+            );
+        }
+        &self.data[r * self.row_stride + c * self.column_stride]
+    }
+}
+impl<T: Float> IndexMut<(usize, usize)> for MatrixViewMut<'_, T> {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut Self::Output {
+        let (rows, columns) = (self.rows, self.columns);
+        match self.get_mut(r, c) {
+            Some(v) => v,
+            None => panic!(
+                "Index ({}, {}) out of bounds for {}x{} view",
+                r, c, rows, columns
+            ),
+        }
+    }
+}
+
+macro_rules! impl_view_op {
+    ($trait:ident, $method:ident, $try_method:ident) => {
+        impl<T: Float> $trait<MatrixView<'_, T>> for MatrixView<'_, T> {
+            type Output = Matrix2d<T>;
+
+            fn $method(self, rhs: MatrixView<'_, T>) -> Self::Output {
+                or_panic(self.$try_method(&rhs))
+            }
+        }
+    };
+}
+impl_view_op!(Add, add, try_add);
+impl_view_op!(Sub, sub, try_sub);
+impl_view_op!(Mul, mul, try_mul);
+impl_view_op!(Div, div, try_div);
+
+macro_rules! impl_matrix_view_op {
+    ($trait:ident, $method:ident, $try_method:ident, $op:tt) => {
+        impl<T: Float> $trait<MatrixView<'_, T>> for Matrix2d<T> {
+            type Output = Self;
+
+            /// Broadcasts like the owned operators, reusing `self` when it already has the output shape.
+            fn $method(mut self, rhs: MatrixView<'_, T>) -> Self::Output {
+                if broadcast_shape(self.shape(), rhs.shape()) != Some(self.shape()) {
+                    return or_panic(self.view().$try_method(&rhs));
+                }
+                for r in 0..self.rows {
+                    for c in 0..self.columns {
+                        self[r][c] = self[r][c] $op rhs.broadcast_at(r, c);
+                    }
+                }
+                self
+            }
+        }
+    };
+}
+impl_matrix_view_op!(Add, add, try_add, +);
+impl_matrix_view_op!(Sub, sub, try_sub, -);
+impl_matrix_view_op!(Mul, mul, try_mul, *);
+impl_matrix_view_op!(Div, div, try_div, /);
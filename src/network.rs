@@ -3,8 +3,6 @@ use std::{
     fs::{self, OpenOptions},
     io::{Read, Write},
     path::PathBuf,
-    process::Output,
-    usize,
 };
 
 use anyhow::Result;
@@ -12,7 +10,10 @@ use num::Float;
 
 use crate::{
     img::Img,
-    matrix::{Matrix2d, Test},
+    matrix::{Matrix2d, MemoryOrder, Test},
+    quantized::{Granularity, QuantizedMatrix},
+    sparse::SparseMatrix,
+    Seed,
 };
 
 #[derive(Debug)]
@@ -23,14 +24,23 @@ pub struct Network<T: Clone> {
     pub learning_rate: T,
     pub hidden_weights: Matrix2d<T>,
     pub output_weights: Matrix2d<T>,
+    /// Every random step of the network derives its generator from this seed.
+    pub seed: Seed,
+    /// Output error of the last training step, kept so the next step reuses the buffer.
+    output_errors: Matrix2d<T>,
 }
 
+/// Independent random streams of a `Network`, see `Seed::derive`.
+const HIDDEN_WEIGHTS_STREAM: u64 = 0;
+const OUTPUT_WEIGHTS_STREAM: u64 = 1;
+
 impl<T: Test + Debug> Network<T> {
-    pub fn new(input: usize, hidden: usize, output: usize, learning_rate: T) -> Result<Self> {
+    /// Networks built from the same seed start with the same weights.
+    pub fn new(input: usize, hidden: usize, output: usize, learning_rate: T, seed: Seed) -> Result<Self> {
         let mut hidden_weights = Matrix2d::<T>::new(hidden, input);
-        hidden_weights.randomize(hidden)?;
+        hidden_weights.randomize(hidden, &mut seed.derive(HIDDEN_WEIGHTS_STREAM).rng())?;
         let mut output_weights = Matrix2d::<T>::new(output, hidden);
-        output_weights.randomize(output)?;
+        output_weights.randomize(output, &mut seed.derive(OUTPUT_WEIGHTS_STREAM).rng())?;
 
         Ok(Self {
             input,
@@ -39,64 +49,92 @@ impl<T: Test + Debug> Network<T> {
             learning_rate,
             hidden_weights,
             output_weights,
+            seed,
+            output_errors: Matrix2d::new(output, 1),
         })
     }
 
+    /// Same network with weights and learning rate in another precision, e.g. `f16` for storage.
+    pub fn cast<U: Test + Debug>(&self) -> Network<U> {
+        Network {
+            input: self.input,
+            hidden: self.hidden,
+            output: self.output,
+            learning_rate: U::from(self.learning_rate).unwrap(),
+            hidden_weights: self.hidden_weights.cast(),
+            output_weights: self.output_weights.cast(),
+            seed: self.seed,
+            output_errors: self.output_errors.cast(),
+        }
+    }
+
     pub fn train(&mut self, input: &Matrix2d<T>, output: &Matrix2d<T>) {
-        let hidden_inputs = self.hidden_weights.dot_par(input);
-        let hidden_outputs = hidden_inputs.apply(&sigmoid);
-        let final_inputs = self.output_weights.dot_par(&hidden_outputs);
-        let final_outputs = final_inputs.apply(&sigmoid);
+        let hidden_inputs = self.hidden_weights.dot(input);
+        let hidden_errors = self.train_output_layer(hidden_inputs, output);
+        self.hidden_weights.gemm(self.learning_rate, &hidden_errors, &input.t(), T::one());
+    }
 
-        let output_errors = (output.clone()) - final_outputs.clone();
-        let transposed_mat = self.output_weights.transpose_par();
-        let hidden_errors = transposed_mat.dot_par(&output_errors);
+    /// Same as `train` for a sparse `input x 1` column, e.g. an MNIST image that is mostly zero pixels.
+    pub fn train_sparse(&mut self, input: &SparseMatrix<T>, output: &Matrix2d<T>) {
+        let input_row = input.transpose();
+        let hidden_inputs = input_row.dot(&self.hidden_weights.t()).transpose();
+        let hidden_errors = self.train_output_layer(hidden_inputs, output);
+        self.hidden_weights
+            .gemm_sparse(self.learning_rate, &hidden_errors, &input_row, T::one());
+    }
 
-        let sigmoid_primed_mat = sigmoid_prime(final_outputs);
-        let multiplied_mat = output_errors.mul_par(&sigmoid_primed_mat);
-        let transposed_mat = hidden_outputs.transpose_par();
-        let dot_mat = multiplied_mat.dot_par(&transposed_mat);
-        let scaled_mat = dot_mat.scale_par(self.learning_rate);
-        let added_mat = scaled_mat + self.output_weights.clone();
+    /// Updates `output_weights` and returns the error term for the hidden layer weights.
+    fn train_output_layer(&mut self, mut hidden_outputs: Matrix2d<T>, output: &Matrix2d<T>) -> Matrix2d<T> {
+        hidden_outputs.apply_mut(&sigmoid);
+        let mut final_outputs = self.output_weights.dot(&hidden_outputs);
+        final_outputs.apply_mut(&sigmoid);
 
-        self.output_weights = added_mat;
+        if self.output_errors.shape() == output.shape() {
+            self.output_errors.view_mut().assign(output);
+        } else {
+            self.output_errors = output.clone();
+        }
+        self.output_errors -= &final_outputs;
+        let mut hidden_errors = self.output_weights.dot_tn(&self.output_errors);
 
-        let sigmoid_primed_mat = sigmoid_prime(hidden_outputs);
+        self.output_errors *= sigmoid_prime(final_outputs);
+        self.output_weights
+            .gemm(self.learning_rate, &self.output_errors, &hidden_outputs.t(), T::one());
 
-        let multiplied_mat = hidden_errors.mul_par(&sigmoid_primed_mat);
-        let transposed_mat = input.transpose_par();
-        let dot_mat = multiplied_mat.dot_par(&transposed_mat);
-        let scaled_mat = dot_mat.scale_par(self.learning_rate);
-        let added_mat = scaled_mat + self.hidden_weights.clone();
-        self.hidden_weights = added_mat;
+        hidden_errors *= sigmoid_prime(hidden_outputs);
+        hidden_errors
     }
 
-    pub fn train_batch_imgs(&mut self, imgs: &Vec<Img<T>>) {
+    pub fn train_batch_imgs(&mut self, imgs: &[Img<T>]) {
         for (i, img) in imgs.iter().enumerate() {
             if i % 100 == 0 {
                 println!("Img No. {i}",);
             }
-            let img_data = img.matrix.flatten(crate::matrix::Axis::Row);
-            let mut output = Matrix2d::new(10, 1);
-            output.fill(T::zero());
+            let img_data = img.matrix.flatten(MemoryOrder::RowMajor);
+            let mut output = Matrix2d::zeros(10, 1);
             output[img.label as usize][0] = T::one();
             self.train(&img_data, &output);
         }
     }
 
     pub fn predict(&self, input_data: &Matrix2d<T>) -> Matrix2d<T> {
-        let hidden_inputs = self.hidden_weights.dot_par(&input_data);
+        self.predict_from_hidden_inputs(self.hidden_weights.dot(input_data))
+    }
+    pub fn predict_sparse(&self, input_data: &SparseMatrix<T>) -> Matrix2d<T> {
+        let hidden_inputs = input_data.transpose().dot(&self.hidden_weights.t()).transpose();
+        self.predict_from_hidden_inputs(hidden_inputs)
+    }
+    fn predict_from_hidden_inputs(&self, hidden_inputs: Matrix2d<T>) -> Matrix2d<T> {
         let hidden_outputs = hidden_inputs.apply(&sigmoid);
-        let final_inputs = self.output_weights.dot_par(&hidden_outputs);
+        let final_inputs = self.output_weights.dot(&hidden_outputs);
         let final_outputs = final_inputs.apply(&sigmoid);
-        let result = softmax(final_outputs);
-        result
+        softmax(final_outputs)
     }
     pub fn predict_img(&self, img: &Img<T>) -> Matrix2d<T> {
-        let img_data = img.matrix.flatten(crate::matrix::Axis::Row);
-        return self.predict(&img_data);
+        let img_data = img.matrix.flatten(MemoryOrder::RowMajor);
+        self.predict(&img_data)
     }
-    pub fn predict_imgs(&mut self, imgs: &Vec<Img<T>>) -> f64 {
+    pub fn predict_imgs(&mut self, imgs: &[Img<T>]) -> f64 {
         let mut correct = 0;
         for img in imgs {
             let prediction = self.predict_img(img);
@@ -108,7 +146,36 @@ impl<T: Test + Debug> Network<T> {
             println!("{}\nGuess: {}", img, guess);
         }
 
-        return 1.0 / (correct as f64);
+        1.0 / (correct as f64)
+    }
+}
+
+/// Inference-only copy of a `Network` whose matrix products run in integer arithmetic.
+///
+/// Weights are `i8` with one scale per row. Activations are quantized to `u8` before every
+/// layer, image inputs and sigmoid outputs are non-negative and use all 256 levels.
+#[derive(Debug, Clone)]
+pub struct QuantizedNetwork {
+    pub hidden_weights: QuantizedMatrix<i8>,
+    pub output_weights: QuantizedMatrix<i8>,
+}
+
+impl<T: Test + Debug> Network<T> {
+    pub fn quantize(&self) -> QuantizedNetwork {
+        QuantizedNetwork {
+            hidden_weights: self.hidden_weights.quantize(Granularity::PerRow),
+            output_weights: self.output_weights.quantize(Granularity::PerRow),
+        }
+    }
+}
+
+impl QuantizedNetwork {
+    pub fn predict(&self, input_data: &Matrix2d<f32>) -> Matrix2d<f32> {
+        let input = QuantizedMatrix::<u8>::quantize(input_data, Granularity::PerTensor);
+        let hidden_outputs = self.hidden_weights.dot(&input).apply(&sigmoid);
+        let hidden_outputs = QuantizedMatrix::<u8>::quantize(&hidden_outputs, Granularity::PerTensor);
+        let final_outputs = self.output_weights.dot(&hidden_outputs).apply(&sigmoid);
+        softmax(final_outputs)
     }
 }
 
@@ -121,17 +188,16 @@ impl<T: Float + ToString + Clone> Network<T> {
         let mut descriptor = OpenOptions::new()
             .create(true)
             .write(true)
+            .truncate(true)
             .open(path.join("descriptor"))?;
         write!(
             descriptor,
-            "{}\n{}\n{}\n",
-            self.input, self.hidden, self.output
+            "{}\n{}\n{}\n{}\n",
+            self.input, self.hidden, self.output, self.seed
         )?;
 
-        self.hidden_weights
-            .save(path.join("hidden").to_str().unwrap())?;
-        self.output_weights
-            .save(path.join("output").to_str().unwrap())?;
+        self.hidden_weights.save_npy(path.join("hidden.npy"))?;
+        self.output_weights.save_npy(path.join("output.npy"))?;
         Ok(())
     }
     pub fn load(&mut self, dirname: &str) -> Result<()> {
@@ -146,9 +212,19 @@ impl<T: Float + ToString + Clone> Network<T> {
         self.input = splitted.next().unwrap().parse()?;
         self.hidden = splitted.next().unwrap().parse()?;
         self.output = splitted.next().unwrap().parse()?;
+        // Networks saved before the seed was stored keep the current one.
+        if let Some(seed) = splitted.next().filter(|s| !s.is_empty()) {
+            self.seed = seed.parse()?;
+        }
 
-        self.hidden_weights = Matrix2d::load(path.join("hidden").to_str().unwrap())?;
-        self.output_weights = Matrix2d::load(path.join("output").to_str().unwrap())?;
+        // Networks saved before the weights were stored as npy have them as text.
+        if path.join("hidden.npy").exists() {
+            self.hidden_weights = Matrix2d::load_npy(path.join("hidden.npy"))?;
+            self.output_weights = Matrix2d::load_npy(path.join("output.npy"))?;
+        } else {
+            self.hidden_weights = Matrix2d::load(path.join("hidden").to_str().unwrap())?;
+            self.output_weights = Matrix2d::load(path.join("output").to_str().unwrap())?;
+        }
 
         Ok(())
     }
@@ -157,27 +233,16 @@ impl<T: Float + ToString + Clone> Network<T> {
 pub fn sigmoid<T: Float>(input: &T) -> T {
     T::one() / (T::one() + (-T::one() * (*input)).exp())
 }
-pub fn sigmoid_prime<T: Float>(m: Matrix2d<T>) -> Matrix2d<T> {
-    let mut ones = Matrix2d::new(m.rows(), m.columns());
-    ones.fill(T::one());
-    let subtraceted = ones - m.clone();
-    let multiplied = m * subtraceted;
-    return multiplied;
+pub fn sigmoid_prime<T: Test>(mut m: Matrix2d<T>) -> Matrix2d<T> {
+    m.apply_mut(&|x: &T| *x * (T::one() - *x));
+    m
 }
 
-pub fn softmax<T: Float>(m: Matrix2d<T>) -> Matrix2d<T> {
-    let mut total = T::zero();
-    for r in 0..m.rows() {
-        for c in 0..m.columns() {
-            total = total + m[r][c].exp();
-        }
-    }
-
-    let mut mat = Matrix2d::new(m.rows(), m.columns());
-    for r in 0..m.rows() {
-        for c in 0..m.columns() {
-            mat[r][c] = m[r][c] / total;
-        }
-    }
-    mat
+/// `exp(x - max) / sum(exp(x - max))`, shifting by the largest element so `exp` cannot overflow.
+pub fn softmax<T: Test>(mut m: Matrix2d<T>) -> Matrix2d<T> {
+    let max = m.max();
+    m.apply_mut(&|x: &T| (*x - max).exp());
+    let total = m.sum();
+    m.apply_mut(&|x: &T| *x / total);
+    m
 }
@@ -0,0 +1,321 @@
+//! Affine 8 bit quantisation, `x ≈ scale * (q - zero_point)`.
+//!
+//! The range of every group of values is widened to include 0 so that 0 is exactly
+//! representable, which keeps zero padding and sparse inputs exact.
+
+use std::{error::Error, fmt::Display};
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use crate::matrix::{or_panic, par_if, Matrix2d, ShapeError};
+
+/// 8 bit storage type of a `QuantizedMatrix`.
+pub trait Quantized: Copy + Send + Sync {
+    const MIN: i32;
+    const MAX: i32;
+    fn to_i32(self) -> i32;
+    /// `q` must already be in `MIN..=MAX`.
+    fn from_i32(q: i32) -> Self;
+}
+
+impl Quantized for i8 {
+    const MIN: i32 = i8::MIN as i32;
+    const MAX: i32 = i8::MAX as i32;
+    fn to_i32(self) -> i32 {
+        self as i32
+    }
+    fn from_i32(q: i32) -> Self {
+        q as i8
+    }
+}
+
+impl Quantized for u8 {
+    const MIN: i32 = u8::MIN as i32;
+    const MAX: i32 = u8::MAX as i32;
+    fn to_i32(self) -> i32 {
+        self as i32
+    }
+    fn from_i32(q: i32) -> Self {
+        q as u8
+    }
+}
+
+/// Which elements share a scale and zero point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    PerTensor,
+    /// One scale per row, for weights whose rows have very different magnitudes.
+    PerRow,
+}
+
+/// Returned by the quantized products.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuantizationError {
+    Shape(ShapeError),
+    /// The `rhs` of a product has one scale per row, but its rows are summed over and have
+    /// to share a scale.
+    Granularity(Granularity),
+    /// The inner dimension is so long that the `i32` accumulators could overflow.
+    InnerDimension {
+        columns: usize,
+        max: usize,
+    },
+}
+
+impl QuantizationError {
+    /// Reports shape errors under the name of the dispatching method.
+    fn renamed(self, operation: &'static str) -> Self {
+        match self {
+            QuantizationError::Shape(e) => QuantizationError::Shape(ShapeError { operation, ..e }),
+            e => e,
+        }
+    }
+}
+
+impl Display for QuantizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuantizationError::Shape(e) => write!(f, "{}", e),
+            QuantizationError::Granularity(g) => {
+                write!(
+                    f,
+                    "rhs of a quantized product must be PerTensor, not {:?}",
+                    g
+                )
+            }
+            QuantizationError::InnerDimension { columns, max } => write!(
+                f,
+                "inner dimension {} of a quantized product is above the limit of {}",
+                columns, max
+            ),
+        }
+    }
+}
+
+impl Error for QuantizationError {}
+
+impl From<ShapeError> for QuantizationError {
+    fn from(e: ShapeError) -> Self {
+        QuantizationError::Shape(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuantizedMatrix<Q> {
+    rows: usize,
+    columns: usize,
+    values: Vec<Q>,
+    /// One entry for `PerTensor`, one per row for `PerRow`.
+    scales: Vec<f32>,
+    zero_points: Vec<i32>,
+    granularity: Granularity,
+}
+
+/// Scale and zero point mapping `min(values, 0)..=max(values, 0)` onto `Q::MIN..=Q::MAX`.
+fn affine_params<Q: Quantized>(values: &[f32]) -> (f32, i32) {
+    let (low, high) = values
+        .iter()
+        .fold((0f32, 0f32), |(low, high), x| (low.min(*x), high.max(*x)));
+    let scale = (high - low) / (Q::MAX - Q::MIN) as f32;
+    if scale == 0. || !scale.is_finite() {
+        return (1., 0);
+    }
+    let zero_point = (Q::MIN as f32 - low / scale).round() as i32;
+    (scale, zero_point.clamp(Q::MIN, Q::MAX))
+}
+
+/// Longest inner dimension of a `Q` by `R` product whose `i32` accumulators cannot overflow.
+///
+/// Centered values span at most `MAX - MIN` of their type.
+pub fn max_inner_dimension<Q: Quantized, R: Quantized>() -> usize {
+    let product = (Q::MAX - Q::MIN) as usize * (R::MAX - R::MIN) as usize;
+    i32::MAX as usize / product
+}
+
+impl<Q: Quantized> QuantizedMatrix<Q> {
+    pub fn quantize(m: &Matrix2d<f32>, granularity: Granularity) -> Self {
+        let (rows, columns) = m.shape();
+        let groups: Vec<&[f32]> = match granularity {
+            Granularity::PerTensor => vec![m.iter().as_slice()],
+            Granularity::PerRow => m.rows_iter().collect(),
+        };
+        let (scales, zero_points): (Vec<f32>, Vec<i32>) =
+            groups.iter().map(|g| affine_params::<Q>(g)).unzip();
+        let mut values = Vec::with_capacity(rows * columns);
+        for (g, group) in groups.iter().enumerate() {
+            values.extend(group.iter().map(|x| {
+                let q = (x / scales[g]).round() as i32 + zero_points[g];
+                Q::from_i32(q.clamp(Q::MIN, Q::MAX))
+            }));
+        }
+        Self {
+            rows,
+            columns,
+            values,
+            scales,
+            zero_points,
+            granularity,
+        }
+    }
+
+    pub fn dequantize(&self) -> Matrix2d<f32> {
+        Matrix2d::from_fn(self.rows, self.columns, |r, c| {
+            let (scale, zero_point) = self.params(r);
+            scale * (self.values[r * self.columns + c].to_i32() - zero_point) as f32
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.columns)
+    }
+    pub fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+    /// Quantized elements in row-major order.
+    pub fn values(&self) -> &[Q] {
+        &self.values
+    }
+    pub fn scales(&self) -> &[f32] {
+        &self.scales
+    }
+    pub fn zero_points(&self) -> &[i32] {
+        &self.zero_points
+    }
+
+    /// Scale and zero point of row `r`.
+    fn params(&self, r: usize) -> (f32, i32) {
+        match self.granularity {
+            Granularity::PerTensor => (self.scales[0], self.zero_points[0]),
+            Granularity::PerRow => (self.scales[r], self.zero_points[r]),
+        }
+    }
+
+    /// The rows of `rhs` are summed over, so they have to share one scale.
+    fn check_dot<R: Quantized>(
+        &self,
+        rhs: &QuantizedMatrix<R>,
+        operation: &'static str,
+    ) -> Result<Vec<i16>, QuantizationError> {
+        if self.columns != rhs.rows {
+            return Err(ShapeError::new(operation, self.shape(), rhs.shape()).into());
+        }
+        if rhs.granularity != Granularity::PerTensor {
+            return Err(QuantizationError::Granularity(rhs.granularity));
+        }
+        let max = max_inner_dimension::<Q, R>();
+        if self.columns > max {
+            return Err(QuantizationError::InnerDimension {
+                columns: self.columns,
+                max,
+            });
+        }
+        let zero_point = rhs.zero_points[0];
+        Ok(rhs
+            .values
+            .iter()
+            .map(|q| (q.to_i32() - zero_point) as i16)
+            .collect())
+    }
+
+    /// Row `r` of the product, accumulated in `i32` and scaled to `f32` at the end.
+    fn dot_row<R: Quantized>(
+        &self,
+        r: usize,
+        rhs: &QuantizedMatrix<R>,
+        rhs_centered: &[i16],
+        out: &mut [f32],
+    ) {
+        let n = rhs.columns;
+        let (scale, zero_point) = self.params(r);
+        let mut acc = vec![0i32; n];
+        for (p, q) in self.values[r * self.columns..(r + 1) * self.columns]
+            .iter()
+            .enumerate()
+        {
+            let a = q.to_i32() - zero_point;
+            if a == 0 {
+                continue;
+            }
+            for (acc, b) in acc.iter_mut().zip(&rhs_centered[p * n..(p + 1) * n]) {
+                *acc += a * *b as i32;
+            }
+        }
+        let scale = scale * rhs.scales[0];
+        for (o, acc) in out.iter_mut().zip(acc) {
+            *o = scale * acc as f32;
+        }
+    }
+
+    pub fn try_dot_serial<R: Quantized>(
+        &self,
+        rhs: &QuantizedMatrix<R>,
+    ) -> Result<Matrix2d<f32>, QuantizationError> {
+        let rhs_centered = self.check_dot(rhs, "quantized_dot")?;
+        let mut new = Matrix2d::zeros(self.rows, rhs.columns);
+        for (r, out) in new.rows_iter_mut().enumerate() {
+            self.dot_row(r, rhs, &rhs_centered, out);
+        }
+        Ok(new)
+    }
+    pub fn dot_serial<R: Quantized>(&self, rhs: &QuantizedMatrix<R>) -> Matrix2d<f32> {
+        or_panic(self.try_dot_serial(rhs))
+    }
+
+    /// Integer matrix product, `rhs` must be quantized `PerTensor`.
+    ///
+    /// The `i32` accumulators hold any inner dimension up to `max_inner_dimension`, about 33000,
+    /// longer ones are reported as `QuantizationError::InnerDimension`.
+    pub fn try_dot<R: Quantized>(
+        &self,
+        rhs: &QuantizedMatrix<R>,
+    ) -> Result<Matrix2d<f32>, QuantizationError> {
+        let result = par_if!(
+            self.values.len() * rhs.columns,
+            self.try_dot_serial(rhs),
+            self.try_dot_par(rhs)
+        );
+        result.map_err(|e| e.renamed("quantized_dot"))
+    }
+    pub fn dot<R: Quantized>(&self, rhs: &QuantizedMatrix<R>) -> Matrix2d<f32> {
+        or_panic(self.try_dot(rhs))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<Q: Quantized> QuantizedMatrix<Q> {
+    pub fn try_dot_par<R: Quantized>(
+        &self,
+        rhs: &QuantizedMatrix<R>,
+    ) -> Result<Matrix2d<f32>, QuantizationError> {
+        let rhs_centered = self.check_dot(rhs, "quantized_dot_par")?;
+        let mut new = Matrix2d::zeros(self.rows, rhs.columns);
+        let n = rhs.columns;
+        if n == 0 {
+            return Ok(new);
+        }
+        new.inner
+            .par_chunks_mut(n)
+            .enumerate()
+            .for_each(|(r, out)| self.dot_row(r, rhs, &rhs_centered, out));
+        Ok(new)
+    }
+    pub fn dot_par<R: Quantized>(&self, rhs: &QuantizedMatrix<R>) -> Matrix2d<f32> {
+        or_panic(self.try_dot_par(rhs))
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    /// Quantizes a copy of the matrix converted to `f32`.
+    pub fn quantize<Q: Quantized>(&self, granularity: Granularity) -> QuantizedMatrix<Q> {
+        QuantizedMatrix::quantize(&self.cast(), granularity)
+    }
+}
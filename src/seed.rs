@@ -0,0 +1,45 @@
+use std::{fmt::Display, str::FromStr};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Root of all randomness in a run, every random operation gets its generator from a `Seed`
+/// so a run can be repeated exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seed(pub u64);
+
+impl Seed {
+    /// A fresh seed for when reproducibility is not needed, store it to repeat the run later.
+    pub fn from_entropy() -> Self {
+        Self(rand::random())
+    }
+
+    pub fn rng(self) -> StdRng {
+        StdRng::seed_from_u64(self.0)
+    }
+
+    /// Seed of the independent generator number `stream`.
+    ///
+    /// Giving every random step its own stream keeps the other steps unchanged when one of
+    /// them draws more or fewer numbers.
+    pub fn derive(self, stream: u64) -> Self {
+        // SplitMix64 finaliser, so neighbouring streams get unrelated seeds.
+        let mut z = self.0 ^ stream.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Self(z ^ (z >> 31))
+    }
+}
+
+impl Display for Seed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Seed {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse().map(Self)
+    }
+}
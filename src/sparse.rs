@@ -0,0 +1,420 @@
+//! Compressed sparse row matrices, for weights and inputs that are mostly zeros.
+//!
+//! Products with dense matrices come as `dot` (sparse times dense), `dot_sparse` (dense times
+//! sparse) and the fused `gemm_sparse`, which dispatch between serial and parallel kernels
+//! like their dense counterparts.
+
+use std::{error::Error, fmt::Display};
+
+use num::Float;
+
+#[cfg(feature = "rayon")]
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use crate::matrix::{or_panic, par_if, AsView, Matrix2d, ShapeError};
+
+/// Returned by `SparseMatrix::from_triplets` for an entry outside the matrix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TripletOutOfBounds {
+    /// `(row, column)` of the offending entry.
+    pub index: (usize, usize),
+    pub shape: (usize, usize),
+}
+
+impl Display for TripletOutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "triplet at ({}, {}) is outside the {}x{} matrix",
+            self.index.0, self.index.1, self.shape.0, self.shape.1
+        )
+    }
+}
+
+impl Error for TripletOutOfBounds {}
+
+/// Compressed sparse row matrix.
+///
+/// The non-zeros of row `r` are `values[row_offsets[r]..row_offsets[r + 1]]`, sorted by
+/// column, with their columns in the same range of `column_indices`. The CSR form of the
+/// transpose is the CSC form of the original, so `transpose` doubles as the CSC conversion.
+#[derive(Debug, Clone)]
+pub struct SparseMatrix<T: Clone> {
+    rows: usize,
+    columns: usize,
+    row_offsets: Vec<usize>,
+    column_indices: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T: Float> SparseMatrix<T> {
+    /// Keeps every element that is not exactly zero.
+    pub fn from_dense(m: &Matrix2d<T>) -> Self {
+        let mut row_offsets = Vec::with_capacity(m.rows() + 1);
+        let mut column_indices = Vec::new();
+        let mut values = Vec::new();
+        row_offsets.push(0);
+        for r in 0..m.rows() {
+            for (c, v) in m[r].iter().enumerate() {
+                if !v.is_zero() {
+                    column_indices.push(c);
+                    values.push(*v);
+                }
+            }
+            row_offsets.push(values.len());
+        }
+        Self {
+            rows: m.rows(),
+            columns: m.columns(),
+            row_offsets,
+            column_indices,
+            values,
+        }
+    }
+
+    /// Builds the matrix from `(row, column, value)` entries, duplicates are summed.
+    pub fn from_triplets(
+        rows: usize,
+        columns: usize,
+        triplets: &[(usize, usize, T)],
+    ) -> Result<Self, TripletOutOfBounds> {
+        if let Some(&(r, c, _)) = triplets
+            .iter()
+            .find(|(r, c, _)| *r >= rows || *c >= columns)
+        {
+            return Err(TripletOutOfBounds {
+                index: (r, c),
+                shape: (rows, columns),
+            });
+        }
+        let mut sorted = triplets.to_vec();
+        sorted.sort_by_key(|&(r, c, _)| (r, c));
+
+        let mut row_offsets = vec![0; rows + 1];
+        let mut column_indices: Vec<usize> = Vec::with_capacity(sorted.len());
+        let mut values: Vec<T> = Vec::with_capacity(sorted.len());
+        let mut last = None;
+        for (r, c, v) in sorted {
+            if last == Some((r, c)) {
+                let sum = values.last_mut().unwrap();
+                *sum = *sum + v;
+                continue;
+            }
+            last = Some((r, c));
+            column_indices.push(c);
+            values.push(v);
+            row_offsets[r + 1] += 1;
+        }
+        for r in 0..rows {
+            row_offsets[r + 1] += row_offsets[r];
+        }
+        Ok(Self {
+            rows,
+            columns,
+            row_offsets,
+            column_indices,
+            values,
+        })
+    }
+
+    pub fn to_dense(&self) -> Matrix2d<T> {
+        let mut m = Matrix2d::zeros(self.rows, self.columns);
+        for r in 0..self.rows {
+            for (c, v) in self.row(r) {
+                m[r][c] = v;
+            }
+        }
+        m
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.columns)
+    }
+    /// Number of stored elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+    pub fn density(&self) -> f64 {
+        self.nnz() as f64 / (self.rows * self.columns) as f64
+    }
+
+    /// `(column, value)` pairs of the stored elements of row `r`.
+    pub fn row(&self, r: usize) -> impl Iterator<Item = (usize, T)> + '_ {
+        let range = self.row_offsets[r]..self.row_offsets[r + 1];
+        self.column_indices[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.values[range].iter().copied())
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> T {
+        let range = self.row_offsets[r]..self.row_offsets[r + 1];
+        match self.column_indices[range.clone()].binary_search(&c) {
+            Ok(i) => self.values[range.start + i],
+            Err(_) => T::zero(),
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut row_offsets = vec![0; self.columns + 1];
+        for &c in &self.column_indices {
+            row_offsets[c + 1] += 1;
+        }
+        for c in 0..self.columns {
+            row_offsets[c + 1] += row_offsets[c];
+        }
+        let mut next = row_offsets.clone();
+        let mut column_indices = vec![0; self.nnz()];
+        let mut values = vec![T::zero(); self.nnz()];
+        for r in 0..self.rows {
+            for (c, v) in self.row(r) {
+                column_indices[next[c]] = r;
+                values[next[c]] = v;
+                next[c] += 1;
+            }
+        }
+        Self {
+            rows: self.columns,
+            columns: self.rows,
+            row_offsets,
+            column_indices,
+            values,
+        }
+    }
+
+    /// Writes row `r` of `self * rhs` into `out`.
+    fn dot_row<R: AsView<T> + ?Sized>(&self, r: usize, rhs: &R, out: &mut [T]) {
+        let rhs = rhs.view();
+        out.fill(T::zero());
+        for (k, v) in self.row(r) {
+            for (o, x) in out.iter_mut().zip(rhs.row_iter(k)) {
+                *o = *o + v * *x;
+            }
+        }
+    }
+
+    fn check_dot<R: AsView<T> + ?Sized>(
+        &self,
+        rhs: &R,
+        operation: &'static str,
+    ) -> Result<Matrix2d<T>, ShapeError> {
+        let rhs = rhs.view();
+        if self.columns != rhs.rows() {
+            return Err(ShapeError::new(operation, self.shape(), rhs.shape()));
+        }
+        Ok(Matrix2d::new(self.rows, rhs.columns()))
+    }
+
+    pub fn try_dot_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Matrix2d<T>, ShapeError> {
+        let mut new = self.check_dot(rhs, "sparse_dot")?;
+        let n = new.columns();
+        for r in 0..self.rows {
+            self.dot_row(r, rhs, &mut new.inner[r * n..(r + 1) * n]);
+        }
+        Ok(new)
+    }
+    pub fn dot_serial<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Matrix2d<T> {
+        or_panic(self.try_dot_serial(rhs))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Float + Send + Sync> SparseMatrix<T> {
+    pub fn try_dot_par<R: AsView<T> + Sync + ?Sized>(
+        &self,
+        rhs: &R,
+    ) -> Result<Matrix2d<T>, ShapeError> {
+        let mut new = self.check_dot(rhs, "sparse_dot_par")?;
+        let n = new.columns();
+        if n == 0 {
+            return Ok(new);
+        }
+        new.inner
+            .par_chunks_mut(n)
+            .enumerate()
+            .for_each(|(r, out)| self.dot_row(r, rhs, out));
+        Ok(new)
+    }
+    pub fn dot_par<R: AsView<T> + Sync + ?Sized>(&self, rhs: &R) -> Matrix2d<T> {
+        or_panic(self.try_dot_par(rhs))
+    }
+}
+
+impl<T: Float + Send + Sync> SparseMatrix<T> {
+    /// Sparse x dense product.
+    pub fn try_dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Result<Matrix2d<T>, ShapeError> {
+        let rhs = rhs.view();
+        let result = par_if!(
+            self.nnz() * rhs.columns(),
+            self.try_dot_serial(&rhs),
+            self.try_dot_par(&rhs)
+        );
+        result.map_err(|e| ShapeError {
+            operation: "sparse_dot",
+            ..e
+        })
+    }
+    pub fn dot<R: AsView<T> + ?Sized>(&self, rhs: &R) -> Matrix2d<T> {
+        or_panic(self.try_dot(rhs))
+    }
+}
+
+impl<T: Float> From<&Matrix2d<T>> for SparseMatrix<T> {
+    fn from(m: &Matrix2d<T>) -> Self {
+        Self::from_dense(m)
+    }
+}
+
+/// `out = alpha * a_row * b + beta * out` for one row `a_row` of the dense operand.
+fn gemm_sparse_row<T: Float>(alpha: T, a_row: &[T], b: &SparseMatrix<T>, beta: T, out: &mut [T]) {
+    if beta.is_zero() {
+        out.fill(T::zero());
+    } else if beta != T::one() {
+        out.iter_mut().for_each(|o| *o = *o * beta);
+    }
+    for (k, a) in a_row.iter().enumerate() {
+        if a.is_zero() {
+            continue;
+        }
+        let scale = alpha * *a;
+        for (c, v) in b.row(k) {
+            out[c] = out[c] + scale * v;
+        }
+    }
+}
+
+impl<T: Float> Matrix2d<T> {
+    fn check_gemm_sparse(
+        &self,
+        a: &Matrix2d<T>,
+        b: &SparseMatrix<T>,
+        operation: &'static str,
+    ) -> Result<(), ShapeError> {
+        if a.columns() != b.rows() {
+            return Err(ShapeError::new(operation, a.shape(), b.shape()));
+        }
+        if self.shape() != (a.rows(), b.columns()) {
+            return Err(ShapeError::new(
+                operation,
+                self.shape(),
+                (a.rows(), b.columns()),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn try_gemm_sparse_serial(
+        &mut self,
+        alpha: T,
+        a: &Matrix2d<T>,
+        b: &SparseMatrix<T>,
+        beta: T,
+    ) -> Result<(), ShapeError> {
+        self.check_gemm_sparse(a, b, "gemm_sparse")?;
+        let n = self.columns();
+        for r in 0..self.rows() {
+            gemm_sparse_row(alpha, &a[r], b, beta, &mut self.inner[r * n..(r + 1) * n]);
+        }
+        Ok(())
+    }
+    pub fn gemm_sparse_serial(&mut self, alpha: T, a: &Matrix2d<T>, b: &SparseMatrix<T>, beta: T) {
+        or_panic(self.try_gemm_sparse_serial(alpha, a, b, beta))
+    }
+
+    pub fn try_dot_sparse_serial(&self, rhs: &SparseMatrix<T>) -> Result<Self, ShapeError> {
+        let mut new = Self::new(self.rows(), rhs.columns());
+        new.try_gemm_sparse_serial(T::one(), self, rhs, T::zero())
+            .map_err(|e| ShapeError {
+                operation: "dot_sparse",
+                ..e
+            })?;
+        Ok(new)
+    }
+    pub fn dot_sparse_serial(&self, rhs: &SparseMatrix<T>) -> Self {
+        or_panic(self.try_dot_sparse_serial(rhs))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    pub fn try_gemm_sparse_par(
+        &mut self,
+        alpha: T,
+        a: &Matrix2d<T>,
+        b: &SparseMatrix<T>,
+        beta: T,
+    ) -> Result<(), ShapeError> {
+        self.check_gemm_sparse(a, b, "gemm_sparse_par")?;
+        let n = self.columns();
+        if n == 0 {
+            return Ok(());
+        }
+        self.inner
+            .par_chunks_mut(n)
+            .enumerate()
+            .for_each(|(r, out)| gemm_sparse_row(alpha, &a[r], b, beta, out));
+        Ok(())
+    }
+    pub fn gemm_sparse_par(&mut self, alpha: T, a: &Matrix2d<T>, b: &SparseMatrix<T>, beta: T) {
+        or_panic(self.try_gemm_sparse_par(alpha, a, b, beta))
+    }
+
+    pub fn try_dot_sparse_par(&self, rhs: &SparseMatrix<T>) -> Result<Self, ShapeError> {
+        let mut new = Self::new(self.rows(), rhs.columns());
+        new.try_gemm_sparse_par(T::one(), self, rhs, T::zero())
+            .map_err(|e| ShapeError {
+                operation: "dot_sparse_par",
+                ..e
+            })?;
+        Ok(new)
+    }
+    pub fn dot_sparse_par(&self, rhs: &SparseMatrix<T>) -> Self {
+        or_panic(self.try_dot_sparse_par(rhs))
+    }
+}
+
+impl<T: Float + Send + Sync> Matrix2d<T> {
+    /// `self = alpha * a.dot(b) + beta * self` with a sparse `b`, the work is proportional to the non-zeros of `b`.
+    pub fn try_gemm_sparse(
+        &mut self,
+        alpha: T,
+        a: &Matrix2d<T>,
+        b: &SparseMatrix<T>,
+        beta: T,
+    ) -> Result<(), ShapeError> {
+        let result = par_if!(
+            a.rows() * b.nnz(),
+            self.try_gemm_sparse_serial(alpha, a, b, beta),
+            self.try_gemm_sparse_par(alpha, a, b, beta)
+        );
+        result.map_err(|e| ShapeError {
+            operation: "gemm_sparse",
+            ..e
+        })
+    }
+    pub fn gemm_sparse(&mut self, alpha: T, a: &Matrix2d<T>, b: &SparseMatrix<T>, beta: T) {
+        or_panic(self.try_gemm_sparse(alpha, a, b, beta))
+    }
+
+    /// Dense x sparse product.
+    pub fn try_dot_sparse(&self, rhs: &SparseMatrix<T>) -> Result<Self, ShapeError> {
+        let result = par_if!(
+            self.rows() * rhs.nnz(),
+            self.try_dot_sparse_serial(rhs),
+            self.try_dot_sparse_par(rhs)
+        );
+        result.map_err(|e| ShapeError {
+            operation: "dot_sparse",
+            ..e
+        })
+    }
+    pub fn dot_sparse(&self, rhs: &SparseMatrix<T>) -> Self {
+        or_panic(self.try_dot_sparse(rhs))
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    ops::{Index, IndexMut},
+};
+
+use num::Float;
+
+use crate::matrix::Matrix2d;
+
+/// Returned by the tensor operations, the N-dimensional counterpart of `ShapeError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TensorError {
+    /// The operand shapes do not fit together, a flat `Vec` counts as a 1 dimensional shape.
+    Shape {
+        operation: &'static str,
+        lhs: Vec<usize>,
+        rhs: Vec<usize>,
+    },
+    /// `permute` got axes that are not a permutation of `0..ndim`.
+    Permutation { axes: Vec<usize>, ndim: usize },
+    /// Only 2 dimensional tensors convert to a `Matrix2d`.
+    NotMatrix { shape: Vec<usize> },
+}
+
+impl Display for TensorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TensorError::Shape {
+                operation,
+                lhs,
+                rhs,
+            } => write!(
+                f,
+                "{}: incompatible shapes {:?} and {:?}",
+                operation, lhs, rhs
+            ),
+            TensorError::Permutation { axes, ndim } => {
+                write!(f, "{:?} is not a permutation of {} axes", axes, ndim)
+            }
+            TensorError::NotMatrix { shape } => {
+                write!(
+                    f,
+                    "only 2 dimensional tensors convert to Matrix2d, got shape {:?}",
+                    shape
+                )
+            }
+        }
+    }
+}
+
+impl Error for TensorError {}
+
+/// N-dimensional strided tensor.
+///
+/// Element `[i0, i1, ..]` lives at `inner[i0 * strides[0] + i1 * strides[1] + ..]`, so
+/// `permute` only has to shuffle `shape` and `strides` and never moves data.
+#[derive(Debug, Clone)]
+pub struct Tensor<T: Clone> {
+    inner: Vec<T>,
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+}
+
+fn contiguous_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+    strides
+}
+
+impl<T: Float> Tensor<T> {
+    pub fn new(shape: &[usize]) -> Self {
+        Self {
+            inner: vec![T::nan(); shape.iter().product()],
+            shape: shape.to_vec(),
+            strides: contiguous_strides(shape),
+        }
+    }
+
+    pub fn from_vec(shape: &[usize], inner: Vec<T>) -> Result<Self, TensorError> {
+        let len: usize = shape.iter().product();
+        if inner.len() != len {
+            return Err(TensorError::Shape {
+                operation: "from_vec",
+                lhs: shape.to_vec(),
+                rhs: vec![inner.len()],
+            });
+        }
+        Ok(Self {
+            inner,
+            shape: shape.to_vec(),
+            strides: contiguous_strides(shape),
+        })
+    }
+
+    pub fn fill(&mut self, n: T) {
+        self.inner.iter_mut().for_each(|x| *x = n);
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+    pub fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn is_contiguous(&self) -> bool {
+        self.strides == contiguous_strides(&self.shape)
+    }
+
+    /// Storage offset of a multi-index, `None` when it is out of bounds.
+    pub fn offset(&self, index: &[usize]) -> Option<usize> {
+        if index.len() != self.shape.len() || index.iter().zip(&self.shape).any(|(i, s)| i >= s) {
+            return None;
+        }
+        Some(index.iter().zip(&self.strides).map(|(i, s)| i * s).sum())
+    }
+
+    /// Multi-index of the `i`-th element in row-major logical order, `None` when `i` is out of
+    /// bounds, which it always is for a tensor with a zero-size dimension.
+    pub fn unravel_index(&self, mut i: usize) -> Option<Vec<usize>> {
+        if i >= self.len() {
+            return None;
+        }
+        let mut index = vec![0; self.shape.len()];
+        for (axis, size) in self.shape.iter().enumerate().rev() {
+            index[axis] = i % size;
+            i /= size;
+        }
+        Some(index)
+    }
+
+    pub fn get(&self, index: &[usize]) -> Option<&T> {
+        self.offset(index).map(|i| &self.inner[i])
+    }
+    pub fn get_mut(&mut self, index: &[usize]) -> Option<&mut T> {
+        self.offset(index).map(|i| &mut self.inner[i])
+    }
+
+    /// Copies the elements into row-major order of the current shape.
+    pub fn to_contiguous(&self) -> Self {
+        if self.is_contiguous() {
+            return self.clone();
+        }
+        let inner = (0..self.len())
+            .filter_map(|i| self.unravel_index(i))
+            .map(|index| self[&index[..]])
+            .collect();
+        Self {
+            inner,
+            shape: self.shape.clone(),
+            strides: contiguous_strides(&self.shape),
+        }
+    }
+
+    /// Reinterprets the elements in row-major order with a new shape of the same size.
+    pub fn reshape(&self, shape: &[usize]) -> Result<Self, TensorError> {
+        if shape.iter().product::<usize>() != self.len() {
+            return Err(TensorError::Shape {
+                operation: "reshape",
+                lhs: self.shape.clone(),
+                rhs: shape.to_vec(),
+            });
+        }
+        let mut new = self.to_contiguous();
+        new.shape = shape.to_vec();
+        new.strides = contiguous_strides(shape);
+        Ok(new)
+    }
+
+    /// Reorders the axes, `axes[i]` is the old axis that becomes axis `i`.
+    pub fn permute(mut self, axes: &[usize]) -> Result<Self, TensorError> {
+        let mut seen = vec![false; self.ndim()];
+        let invalid = || TensorError::Permutation {
+            axes: axes.to_vec(),
+            ndim: self.ndim(),
+        };
+        if axes.len() != self.ndim() {
+            return Err(invalid());
+        }
+        for &axis in axes {
+            if axis >= self.ndim() || seen[axis] {
+                return Err(invalid());
+            }
+            seen[axis] = true;
+        }
+        self.shape = axes.iter().map(|&a| self.shape[a]).collect();
+        self.strides = axes.iter().map(|&a| self.strides[a]).collect();
+        Ok(self)
+    }
+}
+
+impl<T: Float> Index<&[usize]> for Tensor<T> {
+    type Output = T;
+
+    fn index(&self, index: &[usize]) -> &Self::Output {
+        match self.offset(index) {
+            Some(i) => &self.inner[i],
+            None => panic!("Index {:?} out of bounds for shape {:?}", index, self.shape),
+        }
+    }
+}
+impl<T: Float> IndexMut<&[usize]> for Tensor<T> {
+    fn index_mut(&mut self, index: &[usize]) -> &mut Self::Output {
+        match self.offset(index) {
+            Some(i) => &mut self.inner[i],
+            None => panic!("Index {:?} out of bounds for shape {:?}", index, self.shape),
+        }
+    }
+}
+
+impl<T: Float> From<Matrix2d<T>> for Tensor<T> {
+    fn from(m: Matrix2d<T>) -> Self {
+        let shape = [m.rows, m.columns];
+        Self {
+            inner: m.inner,
+            strides: contiguous_strides(&shape),
+            shape: shape.to_vec(),
+        }
+    }
+}
+
+impl<T: Float> TryFrom<Tensor<T>> for Matrix2d<T> {
+    type Error = TensorError;
+
+    fn try_from(t: Tensor<T>) -> Result<Self, TensorError> {
+        if t.ndim() != 2 {
+            return Err(TensorError::NotMatrix { shape: t.shape });
+        }
+        let t = t.to_contiguous();
+        Ok(Self {
+            rows: t.shape[0],
+            columns: t.shape[1],
+            inner: t.inner,
+        })
+    }
+}
@@ -1,32 +1,1147 @@
-use crate::matrix::Matrix2d;
+use crate::{matrix, matrix::Matrix2d};
 
 #[test]
 fn dot() {
+    let m = matrix![3., 2., 4.; 9., 7., 6.];
+    let m2 = matrix![1., 5.; 3., 9.; 7., 4.];
+    println!("{m}\n{m2}");
+
+    let r = m.dot(&m2);
+    println!("{r}");
+    #[cfg(feature = "rayon")]
+    println!("{}", m.dot_par(&m2));
+
+    let t = m2.transpose();
+    println!("{t}");
+    #[cfg(feature = "rayon")]
+    println!("{}", m2.transpose_par());
+
+}
+
+fn filled(rows: usize, columns: usize, seed: usize) -> Matrix2d<f64> {
+    Matrix2d::from_fn(rows, columns, |r, c| ((r * 31 + c * 17 + seed) % 23) as f64 / 7. - 1.5)
+}
+
+fn dot_naive(a: &Matrix2d<f64>, b: &Matrix2d<f64>) -> Matrix2d<f64> {
+    let mut new = Matrix2d::new(a.rows(), b.columns());
+    for r in 0..a.rows() {
+        for c in 0..b.columns() {
+            let mut sum = 0.;
+            for k in 0..a.columns() {
+                sum += a[r][k] * b[k][c];
+            }
+            new[r][c] = sum;
+        }
+    }
+    new
+}
+
+/// The previous `dot_par`, one parallel reduction per output element.
+#[cfg(feature = "rayon")]
+fn dot_par_naive(a: &Matrix2d<f64>, b: &Matrix2d<f64>) -> Matrix2d<f64> {
+    use rayon::prelude::*;
+
+    let values: Vec<f64> = (0..a.rows() * b.columns())
+        .into_par_iter()
+        .map(|i| {
+            let (r, c) = (i / b.columns(), i % b.columns());
+            (0..a.columns()).into_par_iter().map(|k| a[r][k] * b[k][c]).sum()
+        })
+        .collect();
+    let mut new = Matrix2d::new(a.rows(), b.columns());
+    for (i, v) in values.into_iter().enumerate() {
+        new[i / b.columns()][i % b.columns()] = v;
+    }
+    new
+}
+
+fn assert_close(a: &Matrix2d<f64>, b: &Matrix2d<f64>) {
+    assert!(a.compare_dims(b));
+    for r in 0..a.rows() {
+        for c in 0..a.columns() {
+            assert!((a[r][c] - b[r][c]).abs() < 1e-9, "({r}, {c}): {} != {}", a[r][c], b[r][c]);
+        }
+    }
+}
+
+#[test]
+fn dot_blocked_matches_naive() {
+    for (m, k, n) in [(1, 1, 1), (2, 3, 2), (7, 13, 5), (70, 300, 9), (65, 257, 66), (300, 784, 1), (10, 1, 300), (5, 3, 2050)] {
+        let a = filled(m, k, 1);
+        let b = filled(k, n, 2);
+        let expected = dot_naive(&a, &b);
+        assert_close(&a.dot(&b), &expected);
+        #[cfg(feature = "rayon")]
+        assert_close(&a.dot_par(&b), &expected);
+    }
+}
+
+#[test]
+fn transposed_products() {
+    for (m, k, n) in [(1, 1, 1), (2, 3, 2), (7, 13, 5), (70, 300, 9), (300, 784, 1), (10, 1, 300)] {
+        let a = filled(m, k, 1);
+        let b = filled(k, n, 2);
+        let expected = dot_naive(&a, &b);
+        let (at, bt) = (a.transpose(), b.transpose());
+        assert_close(&at.dot_tn(&b), &expected);
+        #[cfg(feature = "rayon")]
+        assert_close(&at.dot_tn_par(&b), &expected);
+        assert_close(&a.dot_nt(&bt), &expected);
+        #[cfg(feature = "rayon")]
+        assert_close(&a.dot_nt_par(&bt), &expected);
+        assert_close(&at.dot_tt(&bt), &expected);
+        #[cfg(feature = "rayon")]
+        assert_close(&at.dot_tt_par(&bt), &expected);
+
+        let mut c = filled(m, n, 3);
+        let mut expected = c.clone();
+        expected.gemm(0.5, &a, &b, 2.);
+        c.gemm(0.5, &at.t(), &bt.t(), 2.);
+        assert_close(&c, &expected);
+    }
+
+    assert_eq!(filled(2, 3, 0).dot_tn(&filled(2, 4, 0)).shape(), (3, 4));
+    let err = filled(2, 3, 0).try_dot_nt(&filled(2, 4, 0)).unwrap_err();
+    assert_eq!(err.operation, "dot_nt");
+    assert_eq!((err.lhs, err.rhs), ((2, 3), (4, 2)));
+}
+
+#[test]
+fn adaptive_dispatch() {
+    use crate::matrix::{parallel_threshold, set_parallel_threshold, DEFAULT_PARALLEL_THRESHOLD, ShapeError};
+
+    // The threshold is process wide, restore it even when an assertion below fails.
+    struct RestoreThreshold;
+    impl Drop for RestoreThreshold {
+        fn drop(&mut self) {
+            set_parallel_threshold(DEFAULT_PARALLEL_THRESHOLD);
+        }
+    }
+    let _restore = RestoreThreshold;
+
+    let a = filled(40, 30, 1);
+    let b = filled(30, 20, 2);
+    let row = filled(1, 30, 3);
+    let expected_dot = a.dot_serial(&b);
+    let expected_apply = a.apply_serial(&|x| x * x);
+    let expected_add = a.clone().try_add_serial(&row).unwrap();
+
+    // Both ends of the threshold give the same results and errors, only the kernel differs.
+    for threshold in [0, usize::MAX] {
+        set_parallel_threshold(threshold);
+        assert_eq!(parallel_threshold(), threshold);
+        assert_close(&a.dot(&b), &expected_dot);
+        assert_close(&a.apply(&|x| x * x), &expected_apply);
+        assert_close(&(a.clone() + row.clone()), &expected_add);
+        let mut c = filled(40, 20, 4);
+        c.gemm(1., &a, &b, 0.);
+        assert_close(&c, &expected_dot);
+        assert_eq!(a.try_dot(&a).unwrap_err(), ShapeError::new("dot", (40, 30), (40, 30)));
+        assert_eq!(a.clone().try_mul(&b).unwrap_err().operation, "mul");
+    }
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture dot_benchmark`.
+#[test]
+#[ignore]
+#[cfg(feature = "rayon")]
+fn dot_benchmark() {
+    use std::time::Instant;
+
+    for (m, k, n) in [(300, 784, 1), (300, 784, 64), (784, 300, 300), (512, 512, 512)] {
+        let a = filled(m, k, 1);
+        let b = filled(k, n, 2);
+        let iterations = 5;
+
+        let time = |f: &dyn Fn() -> Matrix2d<f64>| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                std::hint::black_box(f());
+            }
+            start.elapsed() / iterations
+        };
+        let naive = time(&|| dot_naive(&a, &b));
+        let blocked = time(&|| a.dot(&b));
+        let naive_par = time(&|| dot_par_naive(&a, &b));
+        let blocked_par = time(&|| a.dot_par(&b));
+
+        println!(
+            "{m}x{k} . {k}x{n}: dot {blocked:?} vs naive {naive:?} ({:.1}x), dot_par {blocked_par:?} vs naive {naive_par:?} ({:.1}x)",
+            naive.as_secs_f64() / blocked.as_secs_f64(),
+            naive_par.as_secs_f64() / blocked_par.as_secs_f64(),
+        );
+    }
+}
+
+#[test]
+fn tensor_reshape_permute() {
+    use crate::tensor::{Tensor, TensorError};
+
+    let t = Tensor::from_vec(&[2, 3, 4], (0..24).map(|x| x as f64).collect()).unwrap();
+    assert_eq!(t[&[1, 2, 3][..]], 23.);
+
+    let p = t.clone().permute(&[2, 0, 1]).unwrap();
+    assert_eq!(p.shape(), &[4, 2, 3]);
+    assert!(!p.is_contiguous());
+    assert_eq!(p[&[3, 1, 2][..]], t[&[1, 2, 3][..]]);
+
+    let r = p.reshape(&[4, 6]).unwrap();
+    assert!(r.is_contiguous());
+    assert_eq!(r[&[1, 0][..]], 1.);
+    assert_eq!(r[&[1, 5][..]], 21.);
+    assert_eq!(
+        t.reshape(&[5, 5]).unwrap_err(),
+        TensorError::Shape { operation: "reshape", lhs: vec![2, 3, 4], rhs: vec![5, 5] }
+    );
+    assert_eq!(
+        t.clone().permute(&[0, 0, 1]).unwrap_err(),
+        TensorError::Permutation { axes: vec![0, 0, 1], ndim: 3 }
+    );
+    assert_eq!(Tensor::<f64>::from_vec(&[2, 2], vec![1.]).unwrap_err().to_string(), "from_vec: incompatible shapes [2, 2] and [1]");
+    assert_eq!((t.unravel_index(23), t.unravel_index(24)), (Some(vec![1, 2, 3]), None));
+
+    // Zero-size dimensions are valid, they just have no elements.
+    let empty = Tensor::<f64>::new(&[3, 0, 2]).permute(&[2, 0, 1]).unwrap();
+    assert_eq!(empty.unravel_index(0), None);
+    assert_eq!(empty.reshape(&[0, 4]).unwrap().shape(), &[0, 4]);
+
+    let m: Matrix2d<f64> = Tensor::from(filled(3, 2, 0)).permute(&[1, 0]).unwrap().try_into().unwrap();
+    assert_close(&m, &filled(3, 2, 0).transpose());
+    assert_eq!(Matrix2d::try_from(t).unwrap_err(), TensorError::NotMatrix { shape: vec![2, 3, 4] });
+}
+
+#[test]
+fn views() {
+    use crate::matrix::AsView;
+
+    let mut m = filled(6, 5, 3);
+    let block = m.slice(1..4, 2..);
+    assert_eq!((block.rows(), block.columns()), (3, 3));
+    assert_eq!(block[(0, 0)], m[1][2]);
+    assert_eq!(block.t()[(2, 1)], m[2][4]);
+    assert_eq!(m.column_range(4..).sum(), (0..6).map(|r| m[r][4]).sum::<f64>());
+
+    let b = filled(5, 6, 4);
+    assert_close(&m.t().dot(&b.t()), &b.dot(&m).transpose());
+    assert_close(&m.dot(&b.slice(.., 1..4)), &dot_naive(&m, &b.column_range(1..4).to_matrix()));
+    assert_close(&(m.t() + b.view()), &(m.transpose() + b.clone()));
+    assert_close(&(m.clone() - b.t()), &(m.clone() - b.transpose()));
+    assert_close(&(m.t() / b.view()), &(m.transpose() / b.clone()));
+    // View operands broadcast like owned ones, also when only the right-hand side grows.
+    let (row, column) = (b.row_range(2..3), m.column_range(1..2));
+    assert_close(&(column + row), &(column.to_matrix() + row.to_matrix()));
+    assert_close(&(m.clone() * b.t().column_range(..1)), &(m.clone() * b.row_range(..1).to_matrix().transpose()));
+    assert_close(&(column.to_matrix() / row), &(column.to_matrix() / row.to_matrix()));
+    assert!(m.view().try_div(&b.view()).is_err());
+
+    let top = m.row_range(..1).to_matrix();
+    m.slice_mut(5.., ..).assign(&top);
+    m.slice_mut(.., 0..1).t_mut().fill(0.);
+    assert_eq!(m[5][3], top[0][3]);
+    assert_eq!(m.slice(.., ..1).max(), 0.);
+}
+
+#[test]
+fn broadcasting() {
+    use crate::matrix::broadcast_shape;
+
+    let a = filled(4, 3, 0);
+    let bias = filled(4, 1, 1);
+    let row = filled(1, 3, 2);
+
+    let sum = a.clone() + bias.clone();
+    let diff = row.clone() - a.clone();
+    let prod = bias.clone() * row.clone();
+    let quot = a.clone() / row.clone();
+    for r in 0..4 {
+        for c in 0..3 {
+            assert_eq!(sum[r][c], a[r][c] + bias[r][0]);
+            assert_eq!(diff[r][c], row[0][c] - a[r][c]);
+            assert_eq!(prod[r][c], bias[r][0] * row[0][c]);
+            assert_eq!(quot[r][c], a[r][c] / row[0][c]);
+        }
+    }
+    #[cfg(feature = "rayon")]
+    {
+        assert_close(&a.clone().add_par(&bias), &sum);
+        assert_close(&row.clone().sub_par(&a), &diff);
+        assert_close(&bias.clone().mul_par(&row), &prod);
+        assert_close(&a.clone().div_par(&row), &quot);
+    }
+    assert_eq!(bias.broadcast_to(4, 3)[2][1], bias[2][0]);
+
+    assert_eq!(broadcast_shape((4, 1), (1, 3)), Some((4, 3)));
+    assert_eq!(broadcast_shape((4, 2), (3, 2)), None);
+    let err = std::panic::catch_unwind(|| filled(4, 2, 0) + filled(3, 2, 0)).unwrap_err();
+    assert_eq!(err.downcast_ref::<String>().unwrap(), "add: incompatible shapes 4x2 and 3x2");
+}
+
+#[test]
+fn in_place_and_fused() {
+    let a = filled(5, 4, 0);
+    let b = filled(4, 3, 1);
+    let x = filled(5, 3, 2);
+
+    let mut y = x.clone();
+    y.gemm(0.5, &a, &b, 2.);
+    assert_close(&y, &(a.dot(&b).scale(0.5) + x.scale(2.)));
+    #[cfg(feature = "rayon")]
+    {
+        let mut y_par = x.clone();
+        y_par.gemm_par(0.5, &a, &b.t().t(), 2.);
+        assert_close(&y_par, &y);
+    }
+
+    let mut y = x.clone();
+    y.axpy(-3., &x);
+    assert_close(&y, &x.scale(-2.));
+    #[cfg(feature = "rayon")]
+    y.axpy_par(3., &x);
+    #[cfg(not(feature = "rayon"))]
+    y.axpy_serial(3., &x);
+    assert_close(&y, &x);
+
+    let mut y = x.clone();
+    y += &x;
+    y -= x.scale(0.5);
+    y *= filled(1, 3, 4);
+    y /= &filled(5, 1, 5);
+    assert_close(&y, &(x.scale(1.5) * filled(1, 3, 4) / filled(5, 1, 5)));
+
+    y.scale_mut(2.);
+    y.apply_mut(&|v| v + 1.);
+    #[cfg(feature = "rayon")]
+    {
+        y.scale_mut_par(0.5);
+        y.apply_mut_par(&|v| v - 0.5);
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        y.scale_mut_serial(0.5);
+        y.apply_mut_serial(&|v| v - 0.5);
+    }
+    assert_close(&y, &(x.scale(1.5) * filled(1, 3, 4) / filled(5, 1, 5)));
+}
+
+#[test]
+fn shape_errors() {
+    use crate::matrix::ShapeError;
+
+    let a = filled(2, 3, 0);
+    let b = filled(2, 3, 1);
+    assert_eq!(a.try_dot(&b).unwrap_err(), ShapeError::new("dot", (2, 3), (2, 3)));
+    #[cfg(feature = "rayon")]
+    assert_eq!(a.try_dot_par(&b).unwrap_err().operation, "dot_par");
+    assert!(a.try_dot(&b.t()).is_ok());
+
+    let mut out = filled(3, 3, 0);
+    let err = out.try_gemm(1., &a, &b.t(), 0.).unwrap_err();
+    assert_eq!(err, ShapeError::new("gemm", (3, 3), (2, 2)));
+    assert_eq!(err.to_string(), "gemm: incompatible shapes 3x3 and 2x2");
+
+    assert_eq!(a.clone().try_add(&filled(3, 2, 0)).unwrap_err().rhs, (3, 2));
+    #[cfg(feature = "rayon")]
+    assert_eq!(a.clone().try_mul_par(&filled(1, 2, 0)).unwrap_err().operation, "mul_par");
+    assert!(a.clone().try_sub(&filled(1, 3, 0)).is_ok());
+
+    let mut c = filled(2, 1, 0);
+    assert_eq!(c.try_add_assign(&a).unwrap_err(), ShapeError::new("add_assign", (2, 1), (2, 3)));
+    assert_eq!(c.try_axpy(1., &a).unwrap_err().operation, "axpy");
+
+    let err: anyhow::Error = a.try_broadcast_to(4, 3).unwrap_err().into();
+    assert_eq!(err.to_string(), "broadcast_to: incompatible shapes 2x3 and 4x3");
+}
+
+#[test]
+fn reductions() {
+    use crate::matrix::Axis;
+
     let mut m = Matrix2d::<f64>::new(2, 3);
-    let d = [3., 2., 4., 9., 7., 6.];
-    for r in 0..m.rows() {
-        for c in 0..m.columns() {
-            m[r][c] = d[r * m.columns() + c];
+    for (i, v) in [1., 5., 3., 4., 2., 6.].into_iter().enumerate() {
+        m[i / 3][i % 3] = v;
+    }
+    assert_eq!(m.sum(), 21.);
+    assert_eq!(m.mean(), 3.5);
+    assert!((m.variance() - 35. / 12.).abs() < 1e-12);
+    assert_eq!((m.max(), m.min()), (6., 1.));
+    assert_eq!((m.argmax(), m.argmin()), (Some(5), Some(0)));
+    assert_eq!(Matrix2d::<f64>::new(0, 3).argmax(), None);
+
+    let rows = m.sum_axis(Axis::Row);
+    assert_eq!((rows.rows(), rows.columns()), (2, 1));
+    assert_eq!((rows[0][0], rows[1][0]), (9., 12.));
+    let columns = m.mean_axis(Axis::Collumn);
+    assert_eq!((columns.rows(), columns.columns()), (1, 3));
+    assert_eq!(&columns[0], &[2.5, 3.5, 4.5]);
+    assert_eq!(&m.variance_axis(Axis::Collumn)[0], &[2.25, 2.25, 2.25]);
+    assert_eq!(&m.max_axis(Axis::Collumn)[0], &[4., 5., 6.]);
+    assert_eq!(m.min_axis(Axis::Row)[1][0], 2.);
+    assert_eq!(m.argmax_axis(Axis::Row), vec![Some(1), Some(2)]);
+    assert_eq!(m.argmin_axis(Axis::Collumn), vec![Some(0), Some(1), Some(0)]);
+    let no_columns = Matrix2d::<f64>::zeros(2, 0);
+    assert_eq!(no_columns.argmax_axis(Axis::Row), vec![None, None]);
+    assert_eq!(no_columns.argmin_axis(Axis::Collumn), vec![]);
+
+    #[cfg(feature = "rayon")]
+    {
+        let big = filled(37, 23, 5);
+        for axis in [Axis::Row, Axis::Collumn] {
+            assert_close(&big.sum_axis_par(axis), &big.sum_axis(axis));
+            assert_close(&big.mean_axis_par(axis), &big.mean_axis(axis));
+            assert_close(&big.variance_axis_par(axis), &big.variance_axis(axis));
+            assert_close(&big.max_axis_par(axis), &big.max_axis(axis));
+            assert_close(&big.min_axis_par(axis), &big.min_axis(axis));
+            assert_eq!(big.argmax_axis_par(axis), big.argmax_axis(axis));
+            assert_eq!(big.argmin_axis_par(axis), big.argmin_axis(axis));
+        }
+        assert!((big.sum_par() - big.sum()).abs() < 1e-9);
+        assert!((big.mean_par() - big.mean()).abs() < 1e-12);
+        assert!((big.variance_par() - big.variance()).abs() < 1e-12);
+        assert_eq!((big.max_par(), big.min_par()), (big.max(), big.min()));
+        assert_eq!((big.argmax_par(), big.argmin_par()), (big.argmax(), big.argmin()));
+        let row = filled(1, 5000, 8);
+        assert!((row.sum_par() - row.sum()).abs() < 1e-9);
+        // Ties keep the first index and NaN never wins, as serially.
+        let ties = matrix![f64::NAN, 1., 3., 3., f64::NAN, -2., -2.];
+        assert_eq!((ties.argmax_par(), ties.argmin_par()), (Some(2), Some(5)));
+        assert_eq!((ties.argmax(), ties.argmin()), (Some(2), Some(5)));
+        assert_eq!(Matrix2d::<f64>::zeros(0, 4).argmax_par(), None);
+    }
+}
+
+#[test]
+fn softmax() {
+    use crate::network::softmax;
+
+    // A large input overflows `exp` unless the maximum is subtracted first.
+    let probabilities = softmax(matrix![1f64; 2.; 800.]);
+    assert!((probabilities.sum() - 1.).abs() < 1e-12);
+    assert!(probabilities.inner.iter().all(|p| p.is_finite()));
+    assert_eq!(probabilities.argmax(), Some(2));
+    let probabilities = softmax(filled(7, 1, 30));
+    assert!((probabilities.sum() - 1.).abs() < 1e-12);
+    assert_close(&softmax(matrix![3.; 3.]), &matrix![0.5; 0.5]);
+}
+
+#[test]
+fn decompositions() {
+    use crate::matrix::LinalgError;
+
+    let a = Matrix2d::from_rows(&[[2., 1., 1.], [4., -6., 0.], [-2., 7., 2.]]);
+    let b = Matrix2d::from_rows(&[[5., 1.], [-2., 0.], [9., 3.]]);
+
+    let lu = a.lu().unwrap();
+    assert_close(&lu.l().dot(&lu.u()), &lu.p().dot(&a));
+    assert!((a.determinant().unwrap() - -16.).abs() < 1e-12);
+    let x = a.solve(&b).unwrap();
+    assert_close(&a.dot(&x), &b);
+    assert_close(&a.dot(&a.inverse().unwrap()), &Matrix2d::from_rows(&[[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]));
+
+    let tall = Matrix2d::from_rows(&[[1., 1.], [1., 2.], [1., 3.], [1., 4.]]);
+    let qr = tall.qr().unwrap();
+    assert_close(&qr.q().dot(qr.r()), &tall);
+    assert_close(&qr.q().t().dot(qr.q()), &Matrix2d::from_rows(&[[1., 0.], [0., 1.]]));
+    assert!(qr.r()[1][0] == 0.);
+    let fit = tall.lstsq(&Matrix2d::from_rows(&[[3.], [5.], [7.], [9.]])).unwrap();
+    assert_close(&fit, &Matrix2d::from_rows(&[[1.], [2.]]));
+    let wide = Matrix2d::from_rows(&[[1., 2., 3.], [4., 5., 6.]]);
+    let qr = wide.qr().unwrap();
+    assert_close(&qr.q().dot(qr.r()), &wide);
+
+    let spd = Matrix2d::from_rows(&[[4., 12., -16.], [12., 37., -43.], [-16., -43., 98.]]);
+    let chol = spd.cholesky().unwrap();
+    assert_close(chol.l(), &Matrix2d::from_rows(&[[2., 0., 0.], [6., 1., 0.], [-8., 5., 3.]]));
+    assert_close(&spd.dot(&chol.solve(&b).unwrap()), &b);
+    assert!((chol.determinant() - spd.determinant().unwrap()).abs() < 1e-9);
+
+    let singular = Matrix2d::from_rows(&[[1., 2.], [2., 4.]]);
+    assert_eq!(singular.solve(&Matrix2d::from_rows(&[[1.], [1.]])).unwrap_err(), LinalgError::Singular);
+    assert_eq!(singular.inverse().unwrap_err(), LinalgError::Singular);
+    assert_eq!(singular.determinant().unwrap(), 0.);
+    assert_eq!(a.cholesky().unwrap_err(), LinalgError::NotPositiveDefinite);
+    assert!(matches!(wide.lu(), Err(LinalgError::Shape(_))));
+    assert!(matches!(a.solve(&wide), Err(LinalgError::Shape(_))));
+}
+
+#[test]
+fn eigen_and_svd() {
+    use crate::matrix::{Convergence, LinalgError};
+
+    let a = Matrix2d::from_rows(&[[4., 1., 2.], [1., 3., 0.], [2., 0., 5.]]);
+    let eigen = a.symmetric_eigen(Convergence::default()).unwrap();
+    assert!(eigen.values.windows(2).all(|w| w[0] >= w[1]));
+    assert!((eigen.values.iter().sum::<f64>() - 12.).abs() < 1e-9);
+    let mut diag = Matrix2d::zeros(3, 3);
+    for i in 0..3 {
+        diag[i][i] = eigen.values[i];
+    }
+    assert_close(&eigen.vectors.dot(&diag).dot(&eigen.vectors.t()), &a);
+    assert_close(&eigen.vectors.t().dot(&eigen.vectors), &Matrix2d::from_rows(&[[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]));
+
+    for m in [filled(5, 3, 7), filled(3, 5, 8)] {
+        let svd = m.svd(Convergence::default()).unwrap();
+        assert_eq!(svd.s.len(), 3);
+        assert!(svd.s.windows(2).all(|w| w[0] >= w[1]));
+        let mut s = Matrix2d::zeros(3, 3);
+        for i in 0..3 {
+            s[i][i] = svd.s[i];
         }
+        assert_close(&svd.u.dot(&s).dot(&svd.vt), &m);
+        assert_close(&svd.vt.dot(&svd.vt.t()), &Matrix2d::from_rows(&[[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]));
     }
 
-    let mut m2 = Matrix2d::<f64>::new(3, 2);
-    let d = [1., 5., 3., 9., 7., 4.];
-    for r in 0..m2.rows() {
-        for c in 0..m2.columns() {
-            m2[r][c] = d[r * m2.columns() + c];
+    let strict = Convergence { tolerance: 1e-300, max_sweeps: 1 };
+    assert_eq!(filled(6, 6, 1).svd(strict).unwrap_err(), LinalgError::NoConvergence { sweeps: 1 });
+    let m = filled(6, 4, 2);
+    let sweeps = m.svd(Convergence::default()).unwrap().sweeps;
+    let exact = Convergence { max_sweeps: sweeps, ..Convergence::default() };
+    assert_eq!(m.svd(exact).unwrap().sweeps, sweeps);
+    let none = Convergence { max_sweeps: 0, ..Convergence::default() };
+    assert_eq!(m.svd(none).unwrap_err(), LinalgError::NoConvergence { sweeps: 0 });
+    assert_eq!(a.symmetric_eigen(none).unwrap_err(), LinalgError::NoConvergence { sweeps: 0 });
+    assert_eq!(filled(3, 3, 1).symmetric_eigen(Convergence::default()).unwrap_err(), LinalgError::NotSymmetric);
+}
+
+#[test]
+fn sparse() {
+    use crate::network::Network;
+    use crate::sparse::SparseMatrix;
+
+    let mut dense = filled(6, 5, 9);
+    dense.slice_mut(1..3, ..).fill(0.);
+    dense.slice_mut(.., 3..4).fill(0.);
+    let sparse = SparseMatrix::from_dense(&dense);
+    assert_eq!(sparse.nnz(), dense.inner.iter().filter(|v| **v != 0.).count());
+    assert_close(&sparse.to_dense(), &dense);
+    assert_close(&sparse.transpose().to_dense(), &dense.transpose());
+    assert_eq!(sparse.get(4, 2), dense[4][2]);
+
+    let rhs = filled(5, 4, 10);
+    assert_close(&sparse.dot(&rhs), &dense.dot(&rhs));
+    // Strided right-hand sides: a transposed view and a column range of a wider matrix.
+    let rhs_t = rhs.transpose();
+    assert_eq!(rhs_t.t().row_iter(2).copied().collect::<Vec<_>>(), rhs[2]);
+    assert_close(&sparse.dot(&rhs_t.t()), &dense.dot(&rhs));
+    let wide = filled(5, 7, 13);
+    assert_close(&sparse.dot(&wide.column_range(2..5)), &dense.dot(&wide.column_range(2..5).to_matrix()));
+    assert_eq!(wide.column_range(3..3).row_iter(4).count(), 0);
+    #[cfg(feature = "rayon")]
+    assert_close(&sparse.dot_par(&rhs.t().t()), &dense.dot(&rhs));
+    let lhs = filled(3, 6, 11);
+    assert_close(&lhs.dot_sparse(&sparse), &lhs.dot(&dense));
+    #[cfg(feature = "rayon")]
+    assert_close(&lhs.dot_sparse_par(&sparse), &lhs.dot(&dense));
+    let mut acc = filled(3, 5, 12);
+    acc.gemm_sparse(2., &lhs, &sparse, 0.5);
+    assert_close(&acc, &(lhs.dot(&dense).scale(2.) + filled(3, 5, 12).scale(0.5)));
+    assert_eq!(sparse.try_dot(&lhs).unwrap_err().operation, "sparse_dot");
+    assert_eq!(rhs.try_dot_sparse(&sparse).unwrap_err().operation, "dot_sparse");
+
+    let triplets = SparseMatrix::from_triplets(2, 3, &[(1, 2, 1.), (0, 1, 2.), (1, 2, 3.)]).unwrap();
+    assert_eq!((triplets.nnz(), triplets.get(1, 2), triplets.get(0, 0)), (2, 4., 0.));
+    let outside = SparseMatrix::from_triplets(2, 3, &[(0, 0, 1.), (1, 3, 1.)]).unwrap_err();
+    assert_eq!((outside.index, outside.shape), ((1, 3), (2, 3)));
+
+    let mut input = filled(8, 1, 13);
+    input.slice_mut(2..6, ..).fill(0.);
+    let mut output = Matrix2d::zeros(3, 1);
+    output[1][0] = 1.;
+    let mut net = Network::new(8, 4, 3, 0.5, crate::Seed(3)).unwrap();
+    let mut net_sparse = Network::new(8, 4, 3, 0.5, crate::Seed(3)).unwrap();
+    let input_sparse = SparseMatrix::from_dense(&input);
+    net.train(&input, &output);
+    net_sparse.train_sparse(&input_sparse, &output);
+    assert_close(&net_sparse.hidden_weights, &net.hidden_weights);
+    assert_close(&net_sparse.output_weights, &net.output_weights);
+    assert_close(&net_sparse.predict_sparse(&input_sparse), &net.predict(&input));
+}
+
+#[test]
+fn npy_and_npz() {
+    use crate::matrix::{Endian, NpyDtype, NpyOptions};
+
+    let dir = std::env::temp_dir().join(format!("npy_and_npz_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let m = filled(3, 5, 14);
+
+    m.save_npy(dir.join("m.npy")).unwrap();
+    let bytes = std::fs::read(dir.join("m.npy")).unwrap();
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    assert_eq!((bytes.len() - 3 * 5 * 8) % 64, 0);
+    assert_eq!(Matrix2d::<f64>::load_npy(dir.join("m.npy")).unwrap().inner, m.inner);
+
+    for dtype in [NpyDtype::F16, NpyDtype::BF16, NpyDtype::F32, NpyDtype::F64] {
+        for endian in [Endian::Little, Endian::Big] {
+            for fortran_order in [false, true] {
+                let options = NpyOptions {
+                    dtype,
+                    endian,
+                    fortran_order,
+                };
+                let path = dir.join("options.npy");
+                if (dtype, endian) == (NpyDtype::BF16, Endian::Big) {
+                    assert!(m.save_npy_with(&path, options).is_err());
+                    continue;
+                }
+                m.save_npy_with(&path, options).unwrap();
+                let loaded = Matrix2d::<f64>::load_npy(&path).unwrap();
+                assert_eq!(loaded.shape(), (3, 5));
+                let tolerance = match dtype {
+                    NpyDtype::F16 => 2e-3,
+                    NpyDtype::BF16 => 1e-2,
+                    NpyDtype::F32 => 1e-6,
+                    NpyDtype::F64 => 0.,
+                };
+                for (a, b) in loaded.inner.iter().zip(&m.inner) {
+                    assert!((a - b).abs() <= tolerance, "{options:?}");
+                }
+            }
         }
     }
-    println!("{m}\n{m2}");
 
-    let r = m.dot(&m2);
-    let rp = m.dot_par(&m2);
-    println!("{r}");
-    println!("{rp}");
+    // Header as written by numpy.save(np.arange(6, dtype='>f4').reshape(2, 3, order='F')).
+    let mut npy = b"\x93NUMPY\x01\x00v\x00{'descr': '>f4', 'fortran_order': True, 'shape': (2, 3), }"
+        .to_vec();
+    npy.resize(127, b' ');
+    npy.push(b'\n');
+    for x in [0f32, 3., 1., 4., 2., 5.] {
+        npy.extend(x.to_be_bytes());
+    }
+    let loaded = Matrix2d::<f64>::read_npy(&mut npy.as_slice()).unwrap();
+    assert_eq!(loaded.inner, vec![0., 1., 2., 3., 4., 5.]);
+    assert!(Matrix2d::<f64>::read_npy(&mut &npy[..130]).is_err());
 
-    let t = m2.transpose();
-    let t2 = m2.transpose_par();
-    println!("{t}");
-    println!("{t2}");
+    // Hostile shapes must fail cleanly, neither overflowing nor allocating what the header claims.
+    let header = |shape: &str| {
+        let mut npy = b"\x93NUMPY\x01\x00v\x00".to_vec();
+        npy.extend(format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}").bytes());
+        npy.resize(127, b' ');
+        npy.push(b'\n');
+        npy.extend(1f64.to_le_bytes());
+        npy
+    };
+    let err = Matrix2d::<f64>::read_npy(&mut header("(4294967296, 4294967296)").as_slice()).unwrap_err();
+    assert!(err.to_string().contains("too large"), "{err}");
+    let err = Matrix2d::<f64>::read_npy(&mut header("(100000000, 100000)").as_slice()).unwrap_err();
+    assert!(err.to_string().contains("truncated"), "{err}");
+
+    let path = dir.join("weights.npz");
+    let other = filled(4, 1, 15);
+    Matrix2d::save_npz(&path, &[("hidden", &m), ("output", &other)]).unwrap();
+    let loaded = Matrix2d::<f64>::load_npz(&path).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!((loaded[0].0.as_str(), &loaded[0].1.inner), ("hidden", &m.inner));
+    assert_eq!((loaded[1].0.as_str(), loaded[1].1.shape()), ("output", (4, 1)));
+    assert!(Matrix2d::<f64>::load_npz(dir.join("m.npy")).is_err());
+
+    // Zip64 offsets and sizes next to u64::MAX must fail cleanly instead of overflowing.
+    let archive = std::fs::read(&path).unwrap();
+    let central = u32::from_le_bytes(archive[archive.len() - 6..archive.len() - 2].try_into().unwrap()) as usize;
+    let hostile = |size: Option<u64>, offset: u64| {
+        let mut archive = archive.clone();
+        let mut extra = Vec::new();
+        if let Some(size) = size {
+            archive[central + 20..central + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+            extra.extend(size.to_le_bytes());
+        }
+        archive[central + 42..central + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        extra.extend(offset.to_le_bytes());
+        let mut field = 1u16.to_le_bytes().to_vec();
+        field.extend((extra.len() as u16).to_le_bytes());
+        field.extend(extra);
+        archive[central + 30..central + 32].copy_from_slice(&(field.len() as u16).to_le_bytes());
+        let name_len = u16::from_le_bytes([archive[central + 28], archive[central + 29]]) as usize;
+        archive.splice(central + 46 + name_len..central + 46 + name_len, field);
+        let path = dir.join("hostile.npz");
+        std::fs::write(&path, archive).unwrap();
+        Matrix2d::<f64>::load_npz(&path).unwrap_err().to_string()
+    };
+    assert!(hostile(None, u64::MAX - 2).contains("truncated"));
+    assert!(hostile(Some(u64::MAX - 2), 0).contains("truncated"));
+    assert!(hostile(Some(16), 0).contains("hidden"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn constructors() {
+    let m = matrix![1., 2., 3.; 4., 5., 6.];
+    assert_eq!((m.shape(), &m.inner), ((2, 3), &vec![1., 2., 3., 4., 5., 6.]));
+    assert_eq!(m.inner, Matrix2d::from_vec(2, 3, vec![1., 2., 3., 4., 5., 6.]).inner);
+    assert_eq!(m.inner, Matrix2d::from_fn(2, 3, |r, c| (r * 3 + c + 1) as f64).inner);
+    assert_eq!(matrix![7.].shape(), (1, 1));
+
+    let err = Matrix2d::try_from_vec(2, 3, vec![1.; 5]).unwrap_err();
+    assert_eq!((err.operation, err.lhs, err.rhs), ("from_vec", (2, 3), (1, 5)));
+
+    assert_eq!(Matrix2d::<f64>::zeros(2, 2).inner, vec![0.; 4]);
+    assert_eq!(Matrix2d::<f64>::ones(1, 3).inner, vec![1.; 3]);
+    assert_eq!(Matrix2d::full(3, 1, 2.5).inner, vec![2.5; 3]);
+    assert_close(&Matrix2d::identity(3).dot(&filled(3, 4, 16)), &filled(3, 4, 16));
+
+    let r = Matrix2d::random(4, 5, -0.5, 0.5, &mut crate::Seed(0).rng());
+    assert_eq!(r.shape(), (4, 5));
+    assert!(r.inner.iter().all(|x| (-0.5..0.5).contains(x)));
+}
+
+#[test]
+fn iterators() {
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
+
+    let mut m = matrix![1., 2., 3.; 4., 5., 6.];
+    assert_eq!(m.iter().sum::<f64>(), 21.);
+    let rows: Vec<&[f64]> = m.rows_iter().collect();
+    assert_eq!(rows, [[1., 2., 3.], [4., 5., 6.]]);
+    let columns: Vec<Vec<f64>> = m.columns_iter().map(|c| c.copied().collect()).collect();
+    assert_eq!(columns, [vec![1., 4.], vec![2., 5.], vec![3., 6.]]);
+    assert_eq!(m.enumerate_indexed().nth(4), Some((1, 1, &5.)));
+    assert_eq!(m.columns_iter().len(), 3);
+
+    m.iter_mut().for_each(|x| *x *= 2.);
+    for row in m.rows_iter_mut() {
+        row[0] = 0.;
+    }
+    for x in &mut m {
+        *x += 1.;
+    }
+    assert_eq!((&m).into_iter().copied().collect::<Vec<_>>(), [1., 5., 7., 1., 11., 13.]);
+    #[cfg(feature = "rayon")]
+    {
+        assert_eq!(m.par_iter().sum::<f64>(), 38.);
+        assert_eq!(m.par_rows().map(|r| r[2]).collect::<Vec<_>>(), [7., 13.]);
+    }
+
+    let column: Matrix2d<f64> = m.clone().into_iter().map(|x| x - 1.).collect();
+    assert_eq!((column.shape(), column[1][0]), ((6, 1), 4.));
+    let shaped = Matrix2d::from_shape_iter(3, 2, m.iter().copied());
+    assert_eq!((shaped.shape(), shaped[2][1]), ((3, 2), 13.));
+    let err = Matrix2d::try_from_shape_iter(4, 2, m.iter().copied()).unwrap_err();
+    assert_eq!((err.operation, err.rhs), ("from_shape_iter", (1, 6)));
+
+    let empty = Matrix2d::<f64>::zeros(0, 3);
+    assert_eq!(empty.columns_iter().map(|c| c.count()).sum::<usize>(), 0);
+    assert_eq!(Matrix2d::<f64>::zeros(2, 0).rows_iter().len(), 2);
+}
+
+#[test]
+fn concat_and_split() {
+    use crate::matrix::{Axis, IndexError, ShapeError};
+
+    let a = matrix![1., 2.; 3., 4.];
+    let b = matrix![5., 6.];
+    let c = matrix![7.; 8.];
+
+    let v = Matrix2d::vstack(&[&a, &b]);
+    assert_eq!((v.shape(), &v.inner), ((3, 2), &vec![1., 2., 3., 4., 5., 6.]));
+    let h = Matrix2d::hstack(&[&a, &c, &a]);
+    assert_eq!(h.inner, matrix![1., 2., 7., 1., 2.; 3., 4., 8., 3., 4.].inner);
+    assert_eq!(Matrix2d::concat(&[&a, &c], Axis::Collumn).inner, Matrix2d::hstack(&[&a, &c]).inner);
+    assert_eq!(Matrix2d::<f64>::concat(&[], Axis::Row).shape(), (0, 0));
+    assert_eq!(
+        Matrix2d::try_vstack(&[&a, &c]).unwrap_err(),
+        ShapeError::new("vstack", (2, 2), (2, 1))
+    );
+    assert_eq!(Matrix2d::try_hstack(&[&a, &b]).unwrap_err().operation, "hstack");
+
+    let (top, bottom) = v.split_at(Axis::Row, 2);
+    assert_eq!((top.inner, bottom.inner), (a.inner.clone(), b.inner.clone()));
+    let (left, right) = h.split_at(Axis::Collumn, 2);
+    assert_eq!((left.inner, right.shape()), (a.inner.clone(), (2, 3)));
+    let (all, none) = a.split_at(Axis::Collumn, 2);
+    assert_eq!((all.shape(), none.shape()), ((2, 2), (2, 0)));
+    assert_eq!(
+        a.try_split_at(Axis::Row, 3).unwrap_err(),
+        IndexError { operation: "split_at", index: 3, len: 2 }
+    );
+
+    let chunks = h.chunks(Axis::Collumn, 2);
+    assert_eq!(chunks.iter().map(|m| m.shape()).collect::<Vec<_>>(), [(2, 2), (2, 2), (2, 1)]);
+    let refs: Vec<&Matrix2d<f64>> = chunks.iter().collect();
+    assert_eq!(Matrix2d::hstack(&refs).inner, h.inner);
+    assert_eq!(v.chunks(Axis::Row, 5).len(), 1);
+
+    assert_eq!(v.gather(Axis::Row, &[2, 0, 2]).inner, vec![5., 6., 1., 2., 5., 6.]);
+    assert_eq!(h.gather(Axis::Collumn, &[2, 1]).inner, vec![7., 2., 8., 4.]);
+    assert_eq!(h.gather(Axis::Row, &[]).shape(), (0, 5));
+    assert_eq!(
+        v.try_gather(Axis::Row, &[0, 3]).unwrap_err(),
+        IndexError { operation: "gather", index: 3, len: 3 }
+    );
+    assert_eq!(h.try_gather(Axis::Collumn, &[5]).unwrap_err().len, 5);
+}
+
+#[test]
+fn reshape_and_flatten() {
+    use crate::img::csv_to_imgs_shaped;
+    use crate::matrix::MemoryOrder;
+
+    let m = filled(3, 5, 17);
+    let rows = m.flatten(MemoryOrder::RowMajor);
+    assert_eq!((rows.shape(), &rows.inner), ((15, 1), &m.inner));
+    let columns = m.flatten(MemoryOrder::ColumnMajor);
+    assert_eq!(columns.inner, m.transpose().inner);
+    assert_eq!(columns[1][0], m[1][0]);
+    let row = m.flatten_row(MemoryOrder::RowMajor);
+    assert_eq!((row.shape(), &row.inner), ((1, 15), &m.inner));
+    let row = m.flatten_row(MemoryOrder::ColumnMajor);
+    assert_eq!((row.shape(), &row.inner), ((1, 15), &columns.inner));
+
+    assert_eq!(rows.reshape(3, 5).inner, m.inner);
+    assert_eq!(columns.reshape_with(3, 5, MemoryOrder::ColumnMajor).inner, m.inner);
+    let wide = m.reshape(5, 3);
+    assert_eq!((wide[1][0], wide[4][2]), (m[0][3], m[2][4]));
+    assert_eq!(wide.reshape(1, 15).reshape(3, 5).inner, m.inner);
+    let wide = m.reshape_with(5, 3, MemoryOrder::ColumnMajor);
+    assert_eq!((wide[3][0], wide[0][1]), (m[0][1], m[2][1]));
+    assert_eq!(wide.reshape_with(3, 5, MemoryOrder::ColumnMajor).inner, m.inner);
+    assert_eq!(m.try_reshape(4, 4).unwrap_err(), crate::matrix::ShapeError::new("reshape", (3, 5), (4, 4)));
+
+    let path = std::env::temp_dir().join(format!("reshape_and_flatten_{}.csv", std::process::id()));
+    std::fs::write(&path, "label,pixels\n7,0,32,64,96,128,160\n").unwrap();
+    let imgs = csv_to_imgs_shaped::<f64>(&mut std::fs::File::open(&path).unwrap(), 1, 2, 3).unwrap();
+    assert_eq!(imgs[0].label, 7);
+    assert_eq!(imgs[0].matrix.inner, [0., 32., 64., 96., 128., 160.].map(|p| p / 256.));
+    assert!(csv_to_imgs_shaped::<f64>(&mut std::fs::File::open(&path).unwrap(), 1, 2, 2).is_err());
+    let short = csv_to_imgs_shaped::<f64>(&mut std::fs::File::open(&path).unwrap(), 1, 2, 4);
+    assert!(short.unwrap_err().to_string().contains("6 pixels instead of 2x4"));
+    std::fs::write(&path, "label,pixels\n7,0,32,64,96,128,160,\r\n").unwrap();
+    let imgs = csv_to_imgs_shaped::<f64>(&mut std::fs::File::open(&path).unwrap(), 1, 2, 3).unwrap();
+    assert_eq!(imgs[0].matrix.inner, [0., 32., 64., 96., 128., 160.].map(|p| p / 256.));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn initializers() {
+    use crate::matrix::Initializer;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let stats = |m: &Matrix2d<f64>| (m.mean(), m.variance().sqrt());
+
+    let m: Matrix2d<f64> = Initializer::Uniform { low: 2., high: 3. }.sample(50, 40, &mut rng);
+    assert!(m.iter().all(|x| (2.0..3.0).contains(x)));
+    let (mean, _) = stats(&m);
+    assert!((mean - 2.5).abs() < 0.02, "{mean}");
+
+    let m: Matrix2d<f64> = Initializer::Normal { mean: 1., std_dev: 2. }.sample(100, 100, &mut rng);
+    let (mean, std_dev) = stats(&m);
+    assert!((mean - 1.).abs() < 0.05 && (std_dev - 2.).abs() < 0.05, "{mean} {std_dev}");
+
+    let m: Matrix2d<f64> = Initializer::TruncatedNormal { mean: 0., std_dev: 0.5 }.sample(100, 100, &mut rng);
+    assert!(m.iter().all(|x| x.abs() <= 1.));
+    assert!(m.max() > 0.95 && m.min() < -0.95);
+
+    let limit = (6f64 / (300. + 100.)).sqrt();
+    let m: Matrix2d<f64> = Initializer::XavierUniform.sample(100, 300, &mut rng);
+    assert!(m.iter().all(|x| x.abs() < limit) && m.max() > 0.99 * limit);
+    let (_, std_dev) = stats(&Initializer::<f64>::XavierNormal.sample(100, 300, &mut rng));
+    assert!((std_dev - (2f64 / 400.).sqrt()).abs() < 0.003, "{std_dev}");
+    let m: Matrix2d<f64> = Initializer::HeUniform.sample(100, 300, &mut rng);
+    assert!(m.iter().all(|x| x.abs() < (6f64 / 300.).sqrt()));
+    let (_, std_dev) = stats(&Initializer::<f64>::HeNormal.sample(100, 300, &mut rng));
+    assert!((std_dev - (2f64 / 300.).sqrt()).abs() < 0.003, "{std_dev}");
+
+    for (rows, columns) in [(6, 4), (4, 6), (5, 5)] {
+        let q: Matrix2d<f64> = Initializer::Orthogonal { gain: 2. }.sample(rows, columns, &mut rng);
+        assert_eq!(q.shape(), (rows, columns));
+        let gram = if rows >= columns { q.t().dot(&q) } else { q.dot(&q.t()) };
+        let n = rows.min(columns);
+        assert_close(&gram, &Matrix2d::identity(n).scale(4.));
+    }
+
+    let mut m = Matrix2d::zeros(2, 3);
+    m.initialize(Initializer::Constant(0.25), &mut rng);
+    assert_eq!(m.inner, vec![0.25; 6]);
+
+    let a = Initializer::<f64>::HeNormal.sample(3, 3, &mut StdRng::seed_from_u64(1));
+    let b = Initializer::<f64>::HeNormal.sample(3, 3, &mut StdRng::seed_from_u64(1));
+    assert_eq!(a.inner, b.inner);
+
+    let mut m = Matrix2d::<f64>::new(10, 10);
+    m.randomize(4, &mut rng).unwrap();
+    assert!(m.iter().all(|x| x.abs() <= 0.25));
+}
+
+#[test]
+fn seeded_networks() {
+    use crate::{network::Network, Seed};
+
+    let a = Network::<f64>::new(6, 5, 2, 0.1, Seed(42)).unwrap();
+    let b = Network::<f64>::new(6, 5, 2, 0.1, Seed(42)).unwrap();
+    let c = Network::<f64>::new(6, 5, 2, 0.1, Seed(43)).unwrap();
+    assert_eq!(a.hidden_weights.inner, b.hidden_weights.inner);
+    assert_eq!(a.output_weights.inner, b.output_weights.inner);
+    assert_ne!(a.hidden_weights.inner, c.hidden_weights.inner);
+    assert_ne!(Seed(42).derive(0), Seed(42).derive(1));
+    assert_eq!("42".parse::<Seed>().unwrap(), Seed(42));
+
+    let dir = std::env::temp_dir().join(format!("seeded_networks_{}", std::process::id()));
+    a.save(dir.to_str().unwrap()).unwrap();
+    let mut loaded = Network::<f64>::new(1, 1, 1, 0.1, Seed(0)).unwrap();
+    loaded.load(dir.to_str().unwrap()).unwrap();
+    assert_eq!((loaded.seed, loaded.hidden), (Seed(42), 5));
+
+    // Descriptors written before the seed was stored.
+    std::fs::write(dir.join("descriptor"), "6\n5\n2\n").unwrap();
+    loaded.load(dir.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.seed, Seed(42));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn half_precision() {
+    use crate::half::{bf16, f16};
+    use crate::matrix::NpyDtype;
+    use crate::{network::Network, Seed};
+    use num::Float;
+
+    for bits in 0..=u16::MAX {
+        let h = f16::from_bits(bits);
+        if !h.is_nan() {
+            assert_eq!(f16::from_f32(h.to_f32()).to_bits(), bits);
+        }
+        let b = bf16::from_bits(bits);
+        if !b.is_nan() {
+            assert_eq!(bf16::from_f32(b.to_f32()).to_bits(), bits);
+        }
+    }
+    let ulp = |bits: i32| 2f32.powi(bits);
+    assert_eq!(f16::from_f32(1.).to_bits(), 0x3c00);
+    assert_eq!(f16::from_f32(1. + ulp(-11)), f16::from_f32(1.));
+    assert_eq!(f16::from_f32(1. + 3. * ulp(-11)).to_f32(), 1. + ulp(-9));
+    assert_eq!(f16::from_f32(65504.), f16::MAX);
+    assert!(f16::from_f32(65520.).is_infinite());
+    assert_eq!(f16::from_f32(ulp(-25)).to_bits(), 0);
+    assert_eq!(f16::from_f32(1.5 * ulp(-25)).to_bits(), 1);
+    assert!(f16::from_f32(ulp(-24)).is_subnormal());
+    assert!(f16::from_f32(f32::NAN).is_nan() && bf16::from_f32(f32::NAN).is_nan());
+    assert!(f16::from_f32(-0.).is_sign_negative());
+    assert_eq!(bf16::from_f32(1. + ulp(-8)), bf16::from_f32(1.));
+    assert_eq!(bf16::from_f32(1. + 3. * ulp(-8)).to_f32(), 1. + ulp(-6));
+    assert!((bf16::from_f32(1e38).to_f32() / 1e38 - 1.).abs() <= ulp(-8));
+    assert_eq!((f16::from_f32(1.5) * f16::from_f32(-2.)).to_f32(), -3.);
+    assert_eq!(f16::epsilon().to_f32(), ulp(-10));
+    assert_eq!(bf16::epsilon().to_f32(), ulp(-7));
+    assert_eq!(f16::from_f64(2.5).to_string(), "2.5");
+    // Just above the halfway point between two f16 (bf16) values, but exactly on the halfway
+    // point of two f32 values, so rounding through f32 would round down to even.
+    let above_half = |bits: i32| 1. + 2f64.powi(bits) + 2f64.powi(-40);
+    assert_eq!(f16::from_f64(above_half(-11)).to_f64(), 1. + 2f64.powi(-10));
+    assert_eq!(bf16::from_f64(above_half(-8)).to_f64(), 1. + 2f64.powi(-7));
+    assert_eq!(<f16 as num::NumCast>::from(above_half(-11)).unwrap(), f16::from_f64(above_half(-11)));
+    assert_eq!(f16::from_f64(-above_half(-11)).to_f64(), -1. - 2f64.powi(-10));
+    assert_eq!(f16::from_f64(2f64.powi(-25) + 2f64.powi(-60)).to_bits(), 1);
+    assert_eq!(f16::from_f64(1e300), f16::from_f32(f32::INFINITY));
+
+    // 2048 + 1 is not representable in f16, summing in f16 would get stuck at 2048.
+    let a = Matrix2d::<f16>::ones(2, 3000);
+    let b = Matrix2d::<f16>::ones(3000, 5);
+    assert!(a.dot(&b).iter().all(|x| x.to_f32() == 3000.));
+    #[cfg(feature = "rayon")]
+    assert!(a.dot_par(&b).iter().all(|x| x.to_f32() == 3000.));
+    assert!(a.dot(&b.column_range(..1)).iter().all(|x| x.to_f32() == 3000.));
+    let a = Matrix2d::<bf16>::ones(1, 1000);
+    assert_eq!(a.dot(&a.t())[0][0].to_f32(), 1000.);
+
+    let m = filled(4, 6, 18);
+    let back: Matrix2d<f64> = m.cast::<f16>().cast();
+    assert!(m.iter().zip(back.iter()).all(|(x, y)| (x - y).abs() <= x.abs() * 1e-3));
+    let back: Matrix2d<f64> = m.cast::<bf16>().cast();
+    assert!(m.iter().zip(back.iter()).all(|(x, y)| (x - y).abs() <= x.abs() * 1e-2));
+
+    assert_eq!(NpyDtype::of::<f16>(), NpyDtype::F16);
+    assert_eq!(NpyDtype::of::<bf16>(), NpyDtype::BF16);
+    let path = std::env::temp_dir().join(format!("half_precision_{}.npy", std::process::id()));
+    m.cast::<f16>().save_npy(&path).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 128 + 4 * 6 * 2);
+    assert_eq!(Matrix2d::<f64>::load_npy(&path).unwrap().inner, m.cast::<f16>().cast::<f64>().inner);
+    std::fs::remove_file(&path).unwrap();
+
+    let net = Network::<f32>::new(6, 5, 3, 0.1, Seed(5)).unwrap();
+    let half = net.cast::<f16>();
+    let input = filled(6, 1, 19);
+    let expected = net.predict(&input.cast());
+    let predicted = half.predict(&input.cast()).cast::<f32>();
+    assert!(expected.iter().zip(predicted.iter()).all(|(x, y)| (x - y).abs() < 1e-2));
+
+    // Saved weights take 2 bytes each after the 128 byte npy header.
+    let dir = std::env::temp_dir().join(format!("half_precision_net_{}", std::process::id()));
+    let size = |name: &str| std::fs::metadata(dir.join(name)).unwrap().len();
+    half.save(dir.to_str().unwrap()).unwrap();
+    assert_eq!((size("hidden.npy"), size("output.npy")), (128 + 5 * 6 * 2, 128 + 3 * 5 * 2));
+    let mut loaded = Network::new(1, 1, 1, f16::from_f32(0.1), Seed(0)).unwrap();
+    loaded.load(dir.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.hidden_weights.inner, half.hidden_weights.inner);
+    let brain = net.cast::<bf16>();
+    brain.save(dir.to_str().unwrap()).unwrap();
+    assert_eq!((size("hidden.npy"), size("output.npy")), (128 + 5 * 6 * 2, 128 + 3 * 5 * 2));
+    let mut loaded = Network::new(1, 1, 1, bf16::from_f32(0.1), Seed(0)).unwrap();
+    loaded.load(dir.to_str().unwrap()).unwrap();
+    assert_eq!(loaded.output_weights.inner, brain.output_weights.inner);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn quantized() {
+    use crate::matrix::ShapeError;
+    use crate::quantized::{max_inner_dimension, Granularity, QuantizationError, QuantizedMatrix};
+    use crate::{network::Network, Seed};
+
+    let m = filled(5, 7, 20).cast::<f32>();
+    for granularity in [Granularity::PerTensor, Granularity::PerRow] {
+        let q = QuantizedMatrix::<i8>::quantize(&m, granularity);
+        let scale = q.scales().iter().fold(0f32, |a, b| a.max(*b));
+        assert!(q.dequantize().iter().zip(m.iter()).all(|(a, b)| (a - b).abs() <= scale / 2. + 1e-6));
+        let q = QuantizedMatrix::<u8>::quantize(&m, granularity);
+        assert!(q.dequantize().iter().zip(m.iter()).all(|(a, b)| (a - b).abs() <= q.scales()[0].max(scale)));
+    }
+    let q = QuantizedMatrix::<i8>::quantize(&m, Granularity::PerRow);
+    assert_eq!((q.scales().len(), q.zero_points().len(), q.values().len()), (5, 5, 35));
+
+    // Zero stays exact, and so do integers when the range maps onto whole steps.
+    let exact = matrix![0., 1., 2.; -1., 0., 2.].cast::<f32>();
+    let q = QuantizedMatrix::<u8>::quantize(&exact.scale(85.), Granularity::PerTensor);
+    assert_eq!(q.dequantize().iter().map(|x| x / 85.).collect::<Vec<_>>(), exact.inner);
+    let zeros = QuantizedMatrix::<i8>::quantize(&Matrix2d::zeros(2, 2), Granularity::PerTensor);
+    assert_eq!(zeros.dequantize().inner, vec![0.; 4]);
+
+    let a = filled(6, 9, 21).cast::<f32>();
+    let b = filled(9, 4, 22).cast::<f32>().add_scalar(2.);
+    let qa = QuantizedMatrix::<i8>::quantize(&a, Granularity::PerRow);
+    let qb = QuantizedMatrix::<u8>::quantize(&b, Granularity::PerTensor);
+    let expected = qa.dequantize().dot(&qb.dequantize());
+    assert!(qa.dot(&qb).iter().zip(expected.iter()).all(|(x, y)| (x - y).abs() < 1e-3));
+    #[cfg(feature = "rayon")]
+    assert_eq!(qa.dot_par(&qb).inner, qa.dot(&qb).inner);
+    let exact = a.dot(&b);
+    assert!(qa.dot(&qb).iter().zip(exact.iter()).all(|(x, y)| (x - y).abs() < 0.3));
+    let mismatch = ShapeError::new("quantized_dot", (6, 9), (6, 9));
+    assert_eq!(qa.try_dot(&qa).unwrap_err(), QuantizationError::Shape(mismatch));
+    let per_row = QuantizedMatrix::<i8>::quantize(&b.transpose(), Granularity::PerRow);
+    assert_eq!(qb.try_dot(&per_row).unwrap_err(), QuantizationError::Granularity(Granularity::PerRow));
+    let max = max_inner_dimension::<i8, u8>();
+    assert_eq!(max, 33025);
+    let long = QuantizedMatrix::<i8>::quantize(&Matrix2d::zeros(1, max + 1), Granularity::PerTensor);
+    let tall = QuantizedMatrix::<u8>::quantize(&Matrix2d::zeros(max + 1, 1), Granularity::PerTensor);
+    let overflow = QuantizationError::InnerDimension { columns: max + 1, max };
+    assert_eq!(long.try_dot(&tall).unwrap_err(), overflow);
+    #[cfg(feature = "rayon")]
+    assert_eq!(long.try_dot_par(&tall).unwrap_err(), overflow);
+    let long = QuantizedMatrix::<i8>::quantize(&Matrix2d::from_vec(1, max, vec![-1.; max]), Granularity::PerTensor);
+    let tall = QuantizedMatrix::<u8>::quantize(&Matrix2d::from_vec(max, 1, vec![1.; max]), Granularity::PerTensor);
+    assert!((long.dot(&tall)[0][0] + max as f32).abs() < 1e-3 * max as f32);
+
+    let net = Network::<f64>::new(10, 8, 4, 0.1, Seed(9)).unwrap();
+    let input = filled(10, 1, 23).add_scalar(1.5).scale(0.3);
+    let expected = net.predict(&input).cast::<f32>();
+    let predicted = net.quantize().predict(&input.cast());
+    assert!(expected.iter().zip(predicted.iter()).all(|(x, y)| (x - y).abs() < 1e-2));
+}
+
+#[test]
+fn display() {
+    let m = matrix![1., -2.5, 3.; 40., 5., 0.125];
+    assert_eq!(m.to_string(), "2x3 matrix\n[[    1,  -2.5,     3],\n [   40,     5, 0.125]]");
+    assert_eq!(format!("{m:.2}"), "2x3 matrix\n[[ 1.00, -2.50,  3.00],\n [40.00,  5.00,  0.12]]");
+    assert_eq!(format!("{:6.1}", matrix![1.; 2.]), "2x1 matrix\n[[   1.0],\n [   2.0]]");
+    assert_eq!(Matrix2d::<f64>::zeros(0, 4).to_string(), "0x4 matrix\n[]");
+
+    let big = Matrix2d::from_fn(40, 30, |r, c| (r * 30 + c) as f64);
+    let shown = format!("{big}");
+    let lines: Vec<&str> = shown.lines().collect();
+    assert_eq!(lines.len(), 1 + 7);
+    assert_eq!(lines[0], "40x30 matrix");
+    assert_eq!(lines[1], "[[   0,    1,    2,  ...,   27,   28,   29],");
+    assert_eq!(lines[4], " ...,");
+    assert_eq!(lines[7], " [1170, 1171, 1172,  ..., 1197, 1198, 1199]]");
+
+    let full = format!("{big:#}");
+    assert_eq!(full.lines().count(), 1 + 40);
+    assert!(!full.contains("..."));
+    assert!(format!("{:#.1}", big).ends_with("1199.0]]"));
+}
+
+#[test]
+fn elementwise() {
+    let m = matrix![1., -4.; 0.25, 9.];
+    assert_close(&m.abs(), &matrix![1., 4.; 0.25, 9.]);
+    assert_close(&m.abs().sqrt(), &matrix![1., 2.; 0.5, 3.]);
+    assert_close(&m.powi(2), &matrix![1., 16.; 0.0625, 81.]);
+    assert_close(&m.abs().powf(0.5), &m.abs().sqrt());
+    assert_close(&m.signum(), &matrix![1., -1.; 1., 1.]);
+    assert_close(&m.recip(), &matrix![1., -0.25; 4., 1. / 9.]);
+    assert_close(&m.clamp(0., 2.), &matrix![1., 0.; 0.25, 2.]);
+    assert_close(&m.div_scalar(4.), &matrix![0.25, -1.; 0.0625, 2.25]);
+    assert_close(&m.exp().ln(), &m);
+    assert_close(&m.tanh(), &m.apply(&|x: &f64| x.tanh()));
+    let logs = m.ln();
+    assert!(logs[0][1].is_nan() && logs[1][0] == 0.25f64.ln());
+    assert_eq!(matrix![0.; 1.].ln()[0][0], f64::NEG_INFINITY);
+
+    let mut n = m.clone();
+    n.abs_mut();
+    n.powi_mut(3);
+    n.div_scalar_mut(2.);
+    assert_close(&n, &matrix![0.5, 32.; 0.0078125, 364.5]);
+    assert_close(&m.clamp_serial(-1., 1.), &m.clamp(-1., 1.));
+
+    // Broadcast like `+`, a row against every row.
+    let floor = matrix![0., 1.];
+    assert_close(&m.clone().maximum(&floor), &matrix![1., 1.; 0.25, 9.]);
+    assert_close(&m.clone().minimum(&floor), &matrix![0., -4.; 0., 1.]);
+    assert_close(&matrix![f64::NAN, 1.].maximum(&matrix![2., f64::NAN]), &matrix![2., 1.]);
+    assert_close(&(m.clone() / matrix![2.; 4.]), &matrix![0.5, -2.; 0.0625, 2.25]);
+    let err = m.clone().try_minimum(&matrix![1., 2., 3.]).unwrap_err();
+    assert_eq!((err.operation, err.rhs), ("minimum", (1, 3)));
+
+    #[cfg(feature = "rayon")]
+    {
+        let big = filled(40, 30, 6);
+        assert_close(&big.exp_par(), &big.exp_serial());
+        assert_close(&big.clamp_par(-1., 0.5), &big.clamp(-1., 0.5));
+        assert_close(&big.div_scalar_par(3.), &big.div_scalar(3.));
+        let row = filled(1, 30, 7);
+        assert_close(&big.clone().maximum_par(&row), &big.clone().try_maximum_serial(&row).unwrap());
+        assert_eq!(big.try_minimum_par(&filled(2, 2, 0)).unwrap_err().operation, "minimum_par");
+    }
+}
+
+#[test]
+fn masks() {
+    use crate::matrix::Mask;
+
+    let x = matrix![-1., 2., 0.; 3., -0.5, 4.];
+    let positive = x.gt_scalar(0.);
+    assert_eq!(positive.inner, [false, true, false, true, false, true]);
+    assert_eq!((positive.count(), positive.any(), positive.all()), (3, true, false));
+    assert_eq!((!positive.clone()).count(), 3);
+    assert!(x.lt_scalar(5.).all());
+    let empty: Mask = Matrix2d::<f64>::zeros(0, 3).gt_scalar(0.);
+    assert!(!empty.any() && empty.all());
+
+    // ReLU and its gradient.
+    let relu = Matrix2d::where_(&positive, &x, &Matrix2d::zeros(1, 1));
+    assert_close(&relu, &matrix![0., 2., 0.; 3., 0., 4.]);
+    assert_close(&(matrix![1., 1., 1.; 1., 1., 1.] * positive.to_float()), &matrix![0., 1., 0.; 1., 0., 1.]);
+
+    // Comparisons broadcast, a row against every row.
+    let threshold = matrix![0., 2., 1.];
+    assert_eq!(x.gt(&threshold).inner, [false, false, false, true, false, true]);
+    assert_eq!(x.lt(&threshold).inner, [true, false, true, false, true, false]);
+    let near = x.eq_approx(&matrix![-1.05, 2., 0.; 3., -0.5, 4.2], 0.1);
+    assert_eq!(near.count(), 5);
+    assert!(!matrix![f64::NAN].eq_approx(&matrix![f64::NAN], 1.).any());
+    assert_eq!(x.try_gt(&matrix![1., 2.]).unwrap_err().operation, "gt");
 
+    // Gradient clipping and dropout style fills, the mask broadcasts to `self`.
+    let clipped = x.masked_fill(&x.gt_scalar(2.5), 2.5);
+    assert_close(&clipped, &matrix![-1., 2., 0.; 2.5, -0.5, 2.5]);
+    let mut dropped = x.clone();
+    dropped.masked_fill_mut(&matrix![1., 0., 1.].gt_scalar(0.5), 0.);
+    assert_close(&dropped, &matrix![0., 2., 0.; 0., -0.5, 0.]);
+    let err = x.try_masked_fill(&Matrix2d::zeros(3, 2).gt_scalar(0.), 0.).unwrap_err();
+    assert_eq!((err.operation, err.lhs, err.rhs), ("masked_fill", (2, 3), (3, 2)));
+    let err = Matrix2d::try_where_(&positive, &x, &Matrix2d::zeros(2, 2)).unwrap_err();
+    assert_eq!((err.operation, err.rhs), ("where_", (2, 2)));
 }